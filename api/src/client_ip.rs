@@ -0,0 +1,194 @@
+use ipnet::IpNet;
+use rocket::http::{HeaderMap, Status};
+use rocket::request::{FromRequest, Outcome};
+use rocket::Request;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::fmt::{Display, Formatter};
+use std::hash::BuildHasher;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::OnceLock;
+
+use crate::errors::ApiError;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde", rename_all = "kebab-case")]
+pub enum ForwardedHeader {
+    /// `X-Forwarded-For: <client>, <proxy1>, <proxy2>`
+    #[default]
+    XForwardedFor,
+    /// RFC 7239 `Forwarded: for=<client>;proto=https, for=<proxy1>`
+    Forwarded,
+}
+
+impl ForwardedHeader {
+    fn name(&self) -> &'static str {
+        match self {
+            ForwardedHeader::XForwardedFor => "X-Forwarded-For",
+            ForwardedHeader::Forwarded => "Forwarded",
+        }
+    }
+
+    /// Parses a single hop of the chain, `None` for obfuscated or malformed entries.
+    fn parse_hop(&self, hop: &str) -> Option<IpAddr> {
+        match self {
+            ForwardedHeader::XForwardedFor => parse_node(hop),
+            ForwardedHeader::Forwarded => hop
+                .split(';')
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(key, _)| key.trim().eq_ignore_ascii_case("for"))
+                .and_then(|(_, node)| parse_node(node.trim().trim_matches('"'))),
+        }
+    }
+}
+
+/// Parses `ip`, `ipv4:port` or `[ipv6]:port`.
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let node = node.trim();
+    if let Ok(ip) = node.parse::<IpAddr>() {
+        return Some(ip);
+    }
+    if let Ok(addr) = node.parse::<SocketAddr>() {
+        return Some(addr.ip());
+    }
+
+    node.strip_prefix('[')
+        .and_then(|rest| rest.split_once(']'))
+        .and_then(|(ip, _)| ip.parse().ok())
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde", default)]
+pub struct ClientIpConfig {
+    /// Networks of the load balancers and proxies whose forwarding headers are trusted.
+    pub trusted_proxies: Vec<IpNet>,
+    /// Header the trusted proxies use to forward the client address.
+    pub header: ForwardedHeader,
+}
+
+impl ClientIpConfig {
+    fn is_trusted(&self, ip: &IpAddr) -> bool {
+        self.trusted_proxies.iter().any(|net| net.contains(ip))
+    }
+
+    /// Walks the forwarding chain right to left, starting from the TCP peer, and returns
+    /// the first address that is not a trusted proxy. Addresses left of it are supplied
+    /// by the client and can't be trusted.
+    pub fn resolve(&self, peer: IpAddr, headers: &HeaderMap<'_>) -> IpAddr {
+        if !self.is_trusted(&peer) {
+            return peer;
+        }
+
+        let hops: Vec<&str> = headers
+            .get(self.header.name())
+            .flat_map(|value| value.split(','))
+            .collect();
+
+        let mut client = peer;
+        for hop in hops.iter().rev() {
+            match self.header.parse_hop(hop) {
+                Some(ip) => {
+                    client = ip;
+                    if !self.is_trusted(&ip) {
+                        break;
+                    }
+                }
+                // The proxy forwarded something we can't interpret, stick to the proxy itself
+                None => break,
+            }
+        }
+
+        client
+    }
+}
+
+/// Key the client tags are hashed with, random for each process.
+static CLIENT_TAG_KEY: OnceLock<RandomState> = OnceLock::new();
+
+/// Client address resolved once per request and shared by rate limiting, metrics and logs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+impl ClientIp {
+    pub fn resolve(request: &Request<'_>) -> Option<ClientIp> {
+        *request.local_cache(|| {
+            let peer = request.remote()?.ip();
+            let ip = match request.rocket().state::<ClientIpConfig>() {
+                Some(config) => config.resolve(peer, request.headers()),
+                None => peer,
+            };
+
+            Some(ClientIp(ip))
+        })
+    }
+
+    /// Salted hash of the address, recorded in logs instead of the address itself.
+    pub fn tag(&self) -> ClientTag {
+        ClientTag(
+            CLIENT_TAG_KEY
+                .get_or_init(RandomState::new)
+                .hash_one(self.0),
+        )
+    }
+}
+
+/// Identifies the requests of a client in logs without revealing its address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientTag(u64);
+
+impl Display for ClientTag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ClientIp {
+    type Error = ApiError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match ClientIp::resolve(request) {
+            Some(client_ip) => Outcome::Success(client_ip),
+            None => Outcome::Error((Status::BadRequest, ApiError::FailedToGetClientIp)),
+        }
+    }
+}
+
+#[test]
+fn test_resolve_forwarding_chain() {
+    use rocket::http::Header;
+
+    let config = ClientIpConfig {
+        trusted_proxies: vec!["10.0.0.0/8".parse().unwrap()],
+        header: ForwardedHeader::XForwardedFor,
+    };
+    let proxy: IpAddr = "10.0.0.2".parse().unwrap();
+
+    let mut headers = HeaderMap::new();
+    headers.add(Header::new("X-Forwarded-For", "6.6.6.6, 1.2.3.4"));
+    headers.add(Header::new("X-Forwarded-For", "10.0.0.1"));
+    // Spoofed entry left of the real client is ignored
+    assert_eq!(
+        config.resolve(proxy, &headers),
+        "1.2.3.4".parse::<IpAddr>().unwrap()
+    );
+    // Untrusted peers can't forward anything
+    assert_eq!(
+        config.resolve("8.8.8.8".parse().unwrap(), &headers),
+        "8.8.8.8".parse::<IpAddr>().unwrap()
+    );
+
+    let config = ClientIpConfig {
+        header: ForwardedHeader::Forwarded,
+        ..config
+    };
+    let mut headers = HeaderMap::new();
+    headers.add(Header::new(
+        "Forwarded",
+        r#"for="[2001:db8::17]:4711";proto=https, for=10.0.0.1:8080"#,
+    ));
+    assert_eq!(
+        config.resolve(proxy, &headers),
+        "2001:db8::17".parse::<IpAddr>().unwrap()
+    );
+}
@@ -0,0 +1,145 @@
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock};
+use uuid::Uuid;
+
+use crate::config::app_config;
+use crate::handlers::types::CompilationRequest;
+use crate::utils::lib::timestamp;
+
+static COMPILATION_STORE: OnceLock<CompilationStore> = OnceLock::new();
+
+#[derive(Debug, Clone)]
+struct StoredCompilation {
+    id: Uuid,
+    request: CompilationRequest,
+    expires_at: u64,
+}
+
+/// Sources and settings of recent successful compilations, so that verifying one of the
+/// compiled contracts doesn't need them uploaded again.
+#[derive(Debug)]
+pub struct CompilationStore {
+    /// Oldest first, every entry living for the same `ttl`.
+    compilations: Mutex<VecDeque<StoredCompilation>>,
+    ttl: u64,
+    max_entries: usize,
+    /// Bytes of sources a compilation may have at most to be kept.
+    max_entry_size: usize,
+}
+
+impl CompilationStore {
+    pub fn new(ttl: u64, max_entries: usize, max_entry_size: usize) -> Self {
+        Self {
+            compilations: Mutex::new(VecDeque::new()),
+            ttl,
+            max_entries,
+            max_entry_size,
+        }
+    }
+
+    /// Keeps `request`, evicting the oldest compilations past `max_entries`. Compilations
+    /// with more than `max_entry_size` bytes of sources aren't kept.
+    pub fn insert(&self, request: CompilationRequest) -> Option<Uuid> {
+        self.insert_at(request, timestamp())
+    }
+
+    fn insert_at(&self, request: CompilationRequest, now: u64) -> Option<Uuid> {
+        let size: usize = request
+            .contracts
+            .iter()
+            .map(|file| file.file_name.len() + file.file_content.len())
+            .sum();
+        if size > self.max_entry_size {
+            return None;
+        }
+
+        let id = Uuid::new_v4();
+        let mut compilations = self.compilations.lock().unwrap();
+        Self::purge(&mut compilations, now);
+        while !compilations.is_empty() && compilations.len() >= self.max_entries {
+            compilations.pop_front();
+        }
+
+        compilations.push_back(StoredCompilation {
+            id,
+            request,
+            expires_at: now + self.ttl,
+        });
+        Some(id)
+    }
+
+    pub fn get(&self, id: &Uuid) -> Option<CompilationRequest> {
+        self.get_at(id, timestamp())
+    }
+
+    fn get_at(&self, id: &Uuid, now: u64) -> Option<CompilationRequest> {
+        let mut compilations = self.compilations.lock().unwrap();
+        Self::purge(&mut compilations, now);
+        compilations
+            .iter()
+            .find(|compilation| compilation.id == *id)
+            .map(|compilation| compilation.request.clone())
+    }
+
+    fn purge(compilations: &mut VecDeque<StoredCompilation>, now: u64) {
+        while compilations
+            .front()
+            .is_some_and(|compilation| compilation.expires_at <= now)
+        {
+            compilations.pop_front();
+        }
+    }
+}
+
+/// The process wide store, sized from the `verification` settings.
+pub fn compilation_store() -> &'static CompilationStore {
+    COMPILATION_STORE.get_or_init(|| {
+        let config = &app_config().verification;
+        CompilationStore::new(
+            config.compilation_ttl,
+            config.max_stored_compilations,
+            config.max_stored_compilation_size,
+        )
+    })
+}
+
+#[test]
+fn test_compilation_store_expiry() {
+    use crate::handlers::types::{CompilationConfig, CompiledFile};
+    use crate::verification::OptimizerSettings;
+
+    let request = CompilationRequest {
+        config: CompilationConfig {
+            version: "1.5.6".to_string(),
+            user_libraries: vec![],
+            solc_version: None,
+            optimizer: OptimizerSettings::default(),
+        },
+        contracts: vec![],
+        target_path: None,
+    };
+    let store = CompilationStore::new(60, 2, 32);
+
+    let first = store.insert_at(request.clone(), 0).unwrap();
+    let second = store.insert_at(request.clone(), 10).unwrap();
+    assert!(store.get_at(&first, 59).is_some());
+    assert!(store.get_at(&first, 60).is_none());
+    assert!(store.get_at(&second, 60).is_some());
+
+    // The oldest entry makes room once the store is full
+    let third = store.insert_at(request.clone(), 20).unwrap();
+    let fourth = store.insert_at(request.clone(), 30).unwrap();
+    assert!(store.get_at(&second, 30).is_none());
+    assert!(store.get_at(&third, 30).is_some());
+    assert!(store.get_at(&fourth, 30).is_some());
+
+    // Too large to be kept, without evicting anything
+    let mut large_request = request;
+    large_request.contracts.push(CompiledFile {
+        file_name: "contracts/Large.sol".to_string(),
+        file_content: "contract Large {}".to_string(),
+        is_contract: false,
+    });
+    assert_eq!(store.insert_at(large_request, 30), None);
+    assert!(store.get_at(&third, 30).is_some());
+}
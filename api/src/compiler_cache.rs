@@ -0,0 +1,191 @@
+use rocket::serde::json::serde_json;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::{error, info, warn};
+
+use crate::compiler_registry::{sha256_file, CompilerKind};
+use crate::config::AppConfig;
+use crate::errors::CompilerCacheError;
+use crate::utils::lib::DEFAULT_SOLIDITY_VERSION;
+
+const DEFAULT_MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde", default)]
+pub struct CompilerCacheConfig {
+    /// Local directory holding the compiler binaries listed in the manifest. The cache is
+    /// disabled when unset.
+    pub mirror_dir: Option<PathBuf>,
+    /// Defaults to `manifest.json` in `mirror_dir`.
+    pub manifest: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ManifestEntry {
+    pub kind: CompilerKind,
+    pub version: String,
+    /// Binary path, relative to the mirror directory.
+    pub file: PathBuf,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct CompilerManifest {
+    pub compilers: Vec<ManifestEntry>,
+}
+
+/// Installs compilers from a local mirror into the compilers directory, where the
+/// [`crate::compiler_registry::CompilerRegistry`] picks them up. Every binary is checked
+/// against the SHA-256 in the mirror manifest, both when installed and when reused.
+#[derive(Debug)]
+pub struct CompilerCache {
+    mirror_dir: PathBuf,
+    manifest: CompilerManifest,
+    cache_dir: PathBuf,
+}
+
+impl CompilerCache {
+    pub fn new(
+        mirror_dir: PathBuf,
+        manifest_path: &Path,
+        cache_dir: PathBuf,
+    ) -> Result<Self, CompilerCacheError> {
+        let manifest =
+            std::fs::read_to_string(manifest_path).map_err(CompilerCacheError::ManifestRead)?;
+        let manifest =
+            serde_json::from_str(&manifest).map_err(CompilerCacheError::ManifestParse)?;
+
+        Ok(Self {
+            mirror_dir,
+            manifest,
+            cache_dir,
+        })
+    }
+
+    pub fn from_config(config: &AppConfig) -> Result<Option<Self>, CompilerCacheError> {
+        let Some(mirror_dir) = config.compiler_cache.mirror_dir.clone() else {
+            return Ok(None);
+        };
+        let manifest_path = config
+            .compiler_cache
+            .manifest
+            .clone()
+            .unwrap_or_else(|| mirror_dir.join(DEFAULT_MANIFEST_FILE));
+
+        CompilerCache::new(mirror_dir, &manifest_path, config.compilers_dir.clone()).map(Some)
+    }
+
+    /// Name of the cached binary, the way the compiler registry expects it.
+    fn cached_name(kind: CompilerKind, version: &str) -> String {
+        format!("{}-v{}", kind.name(), version)
+    }
+
+    /// Makes sure the verified `kind` `version` binary is in the cache, returning its path.
+    pub fn install(
+        &self,
+        kind: CompilerKind,
+        version: &str,
+    ) -> Result<PathBuf, CompilerCacheError> {
+        let entry = self
+            .manifest
+            .compilers
+            .iter()
+            .find(|entry| entry.kind == kind && entry.version == version)
+            .ok_or_else(|| CompilerCacheError::NotInManifest(kind.name(), version.to_string()))?;
+
+        let name = CompilerCache::cached_name(kind, version);
+        let target = self.cache_dir.join(&name);
+        if target.is_file() {
+            let sha256 = sha256_file(&target).map_err(CompilerCacheError::Io)?;
+            if sha256.eq_ignore_ascii_case(&entry.sha256) {
+                return Ok(target);
+            }
+            warn!(
+                "Cached {:?} doesn't match the manifest, reinstalling",
+                target
+            );
+        }
+
+        std::fs::create_dir_all(&self.cache_dir).map_err(CompilerCacheError::Io)?;
+        // Copied next to the target first, so a half written binary is never picked up
+        let partial = self.cache_dir.join(format!(".{}.partial", name));
+        std::fs::copy(self.mirror_dir.join(&entry.file), &partial)
+            .map_err(CompilerCacheError::Io)?;
+
+        let sha256 = sha256_file(&partial).map_err(CompilerCacheError::Io)?;
+        if !sha256.eq_ignore_ascii_case(&entry.sha256) {
+            let _ = std::fs::remove_file(&partial);
+            return Err(CompilerCacheError::ChecksumMismatch {
+                file: entry.file.clone(),
+                expected: entry.sha256.clone(),
+                actual: sha256,
+            });
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&partial, std::fs::Permissions::from_mode(0o755))
+                .map_err(CompilerCacheError::Io)?;
+        }
+        std::fs::rename(&partial, &target).map_err(CompilerCacheError::Io)?;
+
+        info!("Installed {} {} into {:?}", kind.name(), version, target);
+        Ok(target)
+    }
+
+    /// Installs the configured zksolc versions and the default solc, logging failures
+    /// instead of aborting so a bad mirror entry doesn't keep the service down.
+    pub fn prewarm(&self, zksolc_versions: &[String]) {
+        let compilers = zksolc_versions
+            .iter()
+            .map(|version| (CompilerKind::Zksolc, version.as_str()))
+            .chain([(CompilerKind::Solc, DEFAULT_SOLIDITY_VERSION)]);
+
+        for (kind, version) in compilers {
+            if let Err(e) = self.install(kind, version) {
+                error!("Failed to cache {} {}: {}", kind.name(), version, e);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_install_verifies_checksum() {
+    let root = std::env::temp_dir().join(format!("compiler-cache-{}", uuid::Uuid::new_v4()));
+    let (mirror_dir, cache_dir) = (root.join("mirror"), root.join("cache"));
+    std::fs::create_dir_all(&mirror_dir).unwrap();
+    std::fs::write(mirror_dir.join("zksolc-linux"), b"zksolc").unwrap();
+    std::fs::write(mirror_dir.join("solc-linux"), b"tampered").unwrap();
+
+    // Both entries carry the checksum of the zksolc binary, so the solc one mismatches
+    let sha256 = sha256_file(&mirror_dir.join("zksolc-linux")).unwrap();
+    let manifest = serde_json::json!({
+        "compilers": [
+            { "kind": "zksolc", "version": "1.5.6", "file": "zksolc-linux", "sha256": sha256 },
+            { "kind": "solc", "version": "0.8.24", "file": "solc-linux", "sha256": sha256 },
+        ]
+    });
+    let manifest_path = mirror_dir.join(DEFAULT_MANIFEST_FILE);
+    std::fs::write(&manifest_path, manifest.to_string()).unwrap();
+
+    let cache = CompilerCache::new(mirror_dir, &manifest_path, cache_dir.clone()).unwrap();
+
+    let path = cache.install(CompilerKind::Zksolc, "1.5.6").unwrap();
+    assert_eq!(path, cache_dir.join("zksolc-v1.5.6"));
+    assert_eq!(std::fs::read(&path).unwrap(), b"zksolc");
+
+    assert!(matches!(
+        cache.install(CompilerKind::Solc, "0.8.24"),
+        Err(CompilerCacheError::ChecksumMismatch { .. })
+    ));
+    assert!(!cache_dir.join("solc-v0.8.24").exists());
+    assert!(matches!(
+        cache.install(CompilerKind::Zksolc, "1.4.0"),
+        Err(CompilerCacheError::NotInManifest(..))
+    ));
+
+    let _ = std::fs::remove_dir_all(root);
+}
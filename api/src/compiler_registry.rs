@@ -0,0 +1,544 @@
+use chrono::{NaiveDate, Utc};
+use rocket::serde::json::Json;
+use rocket::tokio;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::config::{app_config, Admin};
+use crate::errors::ApiError;
+use crate::utils::lib::DEFAULT_ZKSOLC_VERSION;
+
+static COMPILER_REGISTRY: OnceLock<CompilerRegistry> = OnceLock::new();
+
+/// Time a binary gets to print its version before it is killed, its version is then taken
+/// from the file name.
+const VERSION_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde", rename_all = "lowercase")]
+pub enum CompilerKind {
+    Zksolc,
+    Zkvyper,
+    Solc,
+}
+
+impl CompilerKind {
+    // `zksolc` has to be matched before `solc`
+    const ALL: [CompilerKind; 3] = [
+        CompilerKind::Zksolc,
+        CompilerKind::Zkvyper,
+        CompilerKind::Solc,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CompilerKind::Zksolc => "zksolc",
+            CompilerKind::Zkvyper => "zkvyper",
+            CompilerKind::Solc => "solc",
+        }
+    }
+
+    /// Binaries are expected to be named `<kind>`, or `<kind>-<anything>`, e.g.
+    /// `zksolc-linux-amd64-musl-v1.5.6`.
+    fn from_file_name(file_name: &str) -> Option<Self> {
+        CompilerKind::ALL.into_iter().find(|kind| {
+            file_name
+                .strip_prefix(kind.name())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('-'))
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct CompilerInfo {
+    pub kind: CompilerKind,
+    pub version: Version,
+    pub commit: Option<String>,
+    pub sha256: String,
+    pub path: PathBuf,
+}
+
+impl CompilerInfo {
+    /// `0.8.24+commit.e11b9ed9` style version, the plain version if the commit is unknown.
+    pub fn long_version(&self) -> String {
+        match self.commit {
+            Some(ref commit) => format!("{}+commit.{}", self.version, commit),
+            None => self.version.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde", default)]
+pub struct ZksolcVersionPolicy {
+    /// Date from which the version is reported as deprecated.
+    pub deprecated_since: Option<NaiveDate>,
+    /// Date from which the version is no longer accepted.
+    pub removal_date: Option<NaiveDate>,
+    /// Supported solc versions, as a semver requirement, e.g. `">=0.4.12, <=0.8.28"`.
+    pub solc_range: Option<String>,
+    pub features: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde", default)]
+pub struct ZksolcPolicyConfig {
+    /// Version preselected by the plugin, the newest supported one is used when this one
+    /// isn't available.
+    pub default_version: String,
+    /// Per version policies, keyed by zksolc version.
+    pub versions: HashMap<String, ZksolcVersionPolicy>,
+}
+
+impl Default for ZksolcPolicyConfig {
+    fn default() -> Self {
+        Self {
+            default_version: DEFAULT_ZKSOLC_VERSION.to_string(),
+            versions: HashMap::new(),
+        }
+    }
+}
+
+impl ZksolcPolicyConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        for (version, policy) in self.versions.iter() {
+            if let Some(solc_range) = &policy.solc_range {
+                VersionReq::parse(solc_range).map_err(|e| {
+                    format!(
+                        "zksolc_policy.versions.\"{}\".solc_range `{}` is invalid: {}",
+                        version, solc_range, e
+                    )
+                })?;
+            }
+            if let (Some(deprecated_since), Some(removal_date)) =
+                (policy.deprecated_since, policy.removal_date)
+            {
+                if removal_date < deprecated_since {
+                    return Err(format!(
+                        "zksolc_policy.versions.\"{}\" is removed before being deprecated",
+                        version
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn is_removed(&self, version: &str, today: NaiveDate) -> bool {
+        self.versions
+            .get(version)
+            .and_then(|policy| policy.removal_date)
+            .is_some_and(|removal_date| removal_date <= today)
+    }
+}
+
+/// What the plugin gets to know about an available zksolc version.
+#[derive(Debug, Clone, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ZksolcVersionInfo {
+    pub version: String,
+    pub default: bool,
+    pub deprecated: bool,
+    #[serde(flatten)]
+    pub policy: ZksolcVersionPolicy,
+}
+
+/// Finds the first `x.y.z` looking token in `text`, along with the commit hash of a
+/// `+commit.<hash>` build suffix.
+fn parse_version(text: &str) -> Option<(Version, Option<String>)> {
+    text.split(|c: char| c.is_whitespace() || matches!(c, '-' | ',' | '(' | ')'))
+        .find_map(|token| {
+            let token = token.strip_prefix('v').unwrap_or(token);
+            let (version, build) = token.split_once('+').unwrap_or((token, ""));
+            let version = Version::parse(version).ok()?;
+            let commit = build
+                .strip_prefix("commit.")
+                .and_then(|commit| commit.split('.').next())
+                .map(String::from);
+
+            Some((version, commit))
+        })
+}
+
+pub(crate) fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+async fn inspect_compiler(kind: CompilerKind, path: PathBuf) -> Option<CompilerInfo> {
+    let file_name = path.file_name()?.to_str()?;
+
+    // The binary knows best, the file name is the fallback for ones that can't run here
+    let probe = tokio::process::Command::new(&path)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let reported = match tokio::time::timeout(VERSION_PROBE_TIMEOUT, probe).await {
+        Ok(Ok(output)) if output.status.success() => {
+            parse_version(&String::from_utf8_lossy(&output.stdout))
+        }
+        Ok(_) => None,
+        Err(_) => {
+            warn!("{:?} did not report its version in time", path);
+            None
+        }
+    };
+    let Some((version, commit)) = reported.or_else(|| parse_version(file_name)) else {
+        warn!("Skipping compiler {:?}: unknown version", path);
+        return None;
+    };
+
+    let hashed_path = path.clone();
+    let sha256 = tokio::task::spawn_blocking(move || sha256_file(&hashed_path))
+        .await
+        .unwrap_or_else(|e| Err(std::io::Error::other(e)));
+    let sha256 = match sha256 {
+        Ok(sha256) => sha256,
+        Err(e) => {
+            warn!("Skipping compiler {:?}: {}", path, e);
+            return None;
+        }
+    };
+
+    Some(CompilerInfo {
+        kind,
+        version,
+        commit,
+        sha256,
+        path,
+    })
+}
+
+/// Compilers available in the compilers directory. Falls back to the configured zksolc
+/// versions while the directory holds no zksolc binary.
+#[derive(Debug)]
+pub struct CompilerRegistry {
+    directory: PathBuf,
+    fallback_zksolc_versions: Vec<String>,
+    zksolc_policy: ZksolcPolicyConfig,
+    compilers: RwLock<Vec<CompilerInfo>>,
+}
+
+impl CompilerRegistry {
+    pub fn new(
+        directory: PathBuf,
+        fallback_zksolc_versions: Vec<String>,
+        zksolc_policy: ZksolcPolicyConfig,
+    ) -> Self {
+        Self {
+            directory,
+            fallback_zksolc_versions,
+            zksolc_policy,
+            compilers: RwLock::new(vec![]),
+        }
+    }
+
+    /// Rescans the compilers directory, replacing the known compilers. Each binary is run
+    /// with `--version`, and killed if it doesn't answer within [`VERSION_PROBE_TIMEOUT`].
+    pub async fn scan(&self) -> usize {
+        let entries = match std::fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!(
+                    "Failed to read compilers directory {:?}: {}",
+                    self.directory, e
+                );
+                *self.compilers.write().unwrap() = vec![];
+                return 0;
+            }
+        };
+
+        let candidates: Vec<(CompilerKind, PathBuf)> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_file())
+            .filter_map(|entry| {
+                let kind = CompilerKind::from_file_name(entry.file_name().to_str()?)?;
+                Some((kind, entry.path()))
+            })
+            .collect();
+
+        let mut compilers = Vec::with_capacity(candidates.len());
+        for (kind, path) in candidates {
+            compilers.extend(inspect_compiler(kind, path).await);
+        }
+        // Newest first, that's the order `allowed_versions` is presented in
+        compilers.sort_by(|a, b| b.version.cmp(&a.version));
+
+        for compiler in compilers.iter() {
+            info!(
+                "Found {} {} at {:?}",
+                compiler.kind.name(),
+                compiler.version,
+                compiler.path
+            );
+        }
+
+        let count = compilers.len();
+        *self.compilers.write().unwrap() = compilers;
+        count
+    }
+
+    pub fn compilers(&self) -> Vec<CompilerInfo> {
+        self.compilers.read().unwrap().clone()
+    }
+
+    pub fn get(&self, kind: CompilerKind, version: &str) -> Option<CompilerInfo> {
+        let version = Version::parse(version).ok()?;
+        self.compilers
+            .read()
+            .unwrap()
+            .iter()
+            .find(|compiler| compiler.kind == kind && compiler.version == version)
+            .cloned()
+    }
+
+    /// Available versions of `kind`, newest first. zksolc versions past their removal date
+    /// are left out.
+    pub fn versions(&self, kind: CompilerKind) -> Vec<String> {
+        let mut versions: Vec<String> = self
+            .compilers
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|compiler| compiler.kind == kind)
+            .map(|compiler| compiler.version.to_string())
+            .collect();
+
+        if kind == CompilerKind::Zksolc {
+            if versions.is_empty() {
+                versions = self.fallback_zksolc_versions.clone();
+            }
+
+            let today = Utc::now().date_naive();
+            versions.retain(|version| !self.zksolc_policy.is_removed(version, today));
+        }
+
+        versions
+    }
+
+    /// The configured default zksolc version, or the newest supported one when it isn't
+    /// available.
+    pub fn default_zksolc_version(&self) -> Option<String> {
+        Self::pick_default(
+            &self.versions(CompilerKind::Zksolc),
+            &self.zksolc_policy.default_version,
+        )
+    }
+
+    fn pick_default(versions: &[String], default_version: &String) -> Option<String> {
+        if versions.contains(default_version) {
+            Some(default_version.clone())
+        } else {
+            versions.first().cloned()
+        }
+    }
+
+    /// Available zksolc versions, newest first, along with their policies.
+    pub fn zksolc_versions_info(&self) -> Vec<ZksolcVersionInfo> {
+        let versions = self.versions(CompilerKind::Zksolc);
+        let default_version = Self::pick_default(&versions, &self.zksolc_policy.default_version);
+
+        let today = Utc::now().date_naive();
+        versions
+            .iter()
+            .map(|version| {
+                let policy = self
+                    .zksolc_policy
+                    .versions
+                    .get(version)
+                    .cloned()
+                    .unwrap_or_default();
+
+                ZksolcVersionInfo {
+                    version: version.clone(),
+                    default: default_version.as_ref() == Some(version),
+                    deprecated: policy
+                        .deprecated_since
+                        .is_some_and(|deprecated_since| deprecated_since <= today),
+                    policy,
+                }
+            })
+            .collect()
+    }
+
+    /// Warning for compilations using a deprecated zksolc version.
+    pub fn deprecation_warning(&self, version: &str) -> Option<String> {
+        let versions = self.zksolc_versions_info();
+        let info = versions
+            .iter()
+            .find(|info| info.version == version && info.deprecated)?;
+
+        let mut warning = format!("zksolc {} is deprecated", version);
+        if let Some(removal_date) = info.policy.removal_date {
+            warning.push_str(&format!(" and will be removed on {}", removal_date));
+        }
+        if let Some(default) = versions
+            .iter()
+            .find(|info| info.default && info.version != version)
+        {
+            warning.push_str(&format!(", please switch to zksolc {}", default.version));
+        }
+
+        Some(warning)
+    }
+
+    pub fn is_supported(&self, kind: CompilerKind, version: &str) -> bool {
+        self.versions(kind).iter().any(|v| v == version)
+    }
+
+    /// Checks that `solc_version` is a plain `x.y.z` version within the `solc_range` of
+    /// zksolc `zksolc_version`. It ends up in the hardhat config, so nothing else is let through.
+    pub fn check_solc_version(
+        &self,
+        zksolc_version: &str,
+        solc_version: &str,
+    ) -> Result<(), ApiError> {
+        let version = Version::parse(solc_version)
+            .ok()
+            .filter(|version| version.pre.is_empty() && version.build.is_empty())
+            .ok_or_else(|| ApiError::InvalidSolcVersion(solc_version.to_string()))?;
+
+        let solc_range = self
+            .zksolc_policy
+            .versions
+            .get(zksolc_version)
+            .and_then(|policy| policy.solc_range.as_deref())
+            .and_then(|solc_range| VersionReq::parse(solc_range).ok());
+        if solc_range.is_some_and(|solc_range| !solc_range.matches(&version)) {
+            return Err(ApiError::SolcVersionNotSupported(
+                solc_version.to_string(),
+                zksolc_version.to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// The process wide registry, scanned at startup and on `POST /admin/compilers/rescan`.
+pub fn compiler_registry() -> &'static CompilerRegistry {
+    COMPILER_REGISTRY.get_or_init(|| {
+        let config = app_config();
+        CompilerRegistry::new(
+            config.compilers_dir.clone(),
+            config.zksolc_versions.clone(),
+            config.zksolc_policy.clone(),
+        )
+    })
+}
+
+#[get("/admin/compilers")]
+pub fn admin_compilers(_admin: Admin) -> Json<Vec<CompilerInfo>> {
+    Json(compiler_registry().compilers())
+}
+
+#[post("/admin/compilers/rescan")]
+pub async fn admin_rescan_compilers(_admin: Admin) -> Json<Vec<CompilerInfo>> {
+    compiler_registry().scan().await;
+    Json(compiler_registry().compilers())
+}
+
+#[test]
+fn test_parse_compiler_versions() {
+    let (version, commit) =
+        parse_version("solc, the solidity compiler\nVersion: 0.8.24+commit.e11b9ed9.Linux.g++")
+            .unwrap();
+    assert_eq!(version, Version::new(0, 8, 24));
+    assert_eq!(commit.as_deref(), Some("e11b9ed9"));
+
+    let (version, commit) = parse_version("zksolc, the ZKsync Solidity compiler v1.5.6").unwrap();
+    assert_eq!(version, Version::new(1, 5, 6));
+    assert_eq!(commit, None);
+
+    let (version, _) = parse_version("zksolc-linux-amd64-musl-v1.4.1").unwrap();
+    assert_eq!(version, Version::new(1, 4, 1));
+
+    assert_eq!(
+        CompilerKind::from_file_name("zksolc-v1.5.6"),
+        Some(CompilerKind::Zksolc)
+    );
+    assert_eq!(
+        CompilerKind::from_file_name("solc-0.8.24"),
+        Some(CompilerKind::Solc)
+    );
+    assert_eq!(CompilerKind::from_file_name("solcjs"), None);
+}
+
+#[test]
+fn test_zksolc_version_policies() {
+    let today = Utc::now().date_naive();
+    let date = |days_from_today: i64| today + chrono::Duration::days(days_from_today);
+    let policy = |deprecated_since: i64, removal_date: i64| ZksolcVersionPolicy {
+        deprecated_since: Some(date(deprecated_since)),
+        removal_date: Some(date(removal_date)),
+        ..Default::default()
+    };
+    let registry = CompilerRegistry::new(
+        PathBuf::new(),
+        ["1.5.6", "1.5.5", "1.4.1", "1.4.0"]
+            .map(String::from)
+            .to_vec(),
+        ZksolcPolicyConfig {
+            default_version: "1.5.5".to_string(),
+            versions: HashMap::from([
+                (
+                    "1.5.6".to_string(),
+                    ZksolcVersionPolicy {
+                        solc_range: Some(">=0.4.12, <=0.8.28".to_string()),
+                        ..Default::default()
+                    },
+                ),
+                ("1.4.1".to_string(), policy(-10, 30)),
+                ("1.4.0".to_string(), policy(-30, -1)),
+            ]),
+        },
+    );
+
+    assert!(!registry.is_supported(CompilerKind::Zksolc, "1.4.0"));
+
+    assert!(registry.check_solc_version("1.5.6", "0.8.24").is_ok());
+    assert!(registry.check_solc_version("1.5.5", "0.8.30").is_ok());
+    assert!(matches!(
+        registry.check_solc_version("1.5.6", "0.8.30"),
+        Err(ApiError::SolcVersionNotSupported(_, _))
+    ));
+    for solc_version in ["0.8", "v0.8.24", "0.8.24-nightly", "0.8.24\", x: 1 }; //"] {
+        assert!(matches!(
+            registry.check_solc_version("1.5.6", solc_version),
+            Err(ApiError::InvalidSolcVersion(_))
+        ));
+    }
+
+    let versions = registry.zksolc_versions_info();
+    let default: Vec<&str> = versions
+        .iter()
+        .filter(|info| info.default)
+        .map(|info| info.version.as_str())
+        .collect();
+    assert_eq!(default, ["1.5.5"]);
+    assert_eq!(registry.default_zksolc_version().as_deref(), Some("1.5.5"));
+    assert!(versions[2].deprecated);
+
+    assert_eq!(registry.deprecation_warning("1.5.6"), None);
+    assert_eq!(
+        registry.deprecation_warning("1.4.1").unwrap(),
+        format!(
+            "zksolc 1.4.1 is deprecated and will be removed on {}, please switch to zksolc 1.5.5",
+            date(30)
+        )
+    );
+}
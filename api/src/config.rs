@@ -0,0 +1,260 @@
+use rocket::figment::providers::Env;
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome};
+use rocket::serde::json::{serde_json, Json, Value};
+use rocket::{Config, Request};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::client_ip::ClientIpConfig;
+use crate::compiler_cache::CompilerCacheConfig;
+use crate::compiler_registry::ZksolcPolicyConfig;
+use crate::cors::CorsConfig;
+use crate::errors::{ApiError, CoreError};
+use crate::networks::{default_networks, validate_networks, NetworkConfig};
+use crate::rate_limiter::RateLimiterConfig;
+use crate::tracing_log::LoggingConfig;
+use crate::utils::lib::COMPILERS_ROOT;
+use crate::verification::VerificationConfig;
+
+/// Environment variables predating the configuration file, mapped to the keys they
+/// override.
+const ENV_OVERRIDES: [(&str, &str); 8] = [
+    ("WORKER_THREADS", "worker.threads"),
+    ("QUEUE_SIZE", "worker.queue_size"),
+    ("METRICS_PORT", "metrics_port"),
+    ("VITE_URL", "cors.vite_url"),
+    ("LOG_TYPE", "logging.format"),
+    ("LOG_LEVEL", "logging.level"),
+    ("SERVICE_VERSION", "service_version"),
+    ("ADMIN_TOKEN", "admin_token"),
+];
+
+/// Last `_` separated segments of the keys whose values are hidden by the admin endpoint.
+const REDACTED_KEY_SEGMENTS: [&str; 4] = ["token", "secret", "password", "key"];
+const REDACTED_VALUE: &str = "<redacted>";
+
+static APP_CONFIG: OnceLock<AppConfig> = OnceLock::new();
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde", default)]
+pub struct WorkerConfig {
+    /// Number of workers executing queued commands.
+    pub threads: u32,
+    /// Maximum number of queued commands.
+    pub queue_size: usize,
+    /// Maximum number of concurrently running hardhat processes.
+    pub spawn_limit: usize,
+    /// Seconds a job result is kept after completion.
+    pub result_ttl: u64,
+}
+
+impl Default for WorkerConfig {
+    fn default() -> Self {
+        Self {
+            threads: 2,
+            queue_size: 1_000,
+            spawn_limit: 8,
+            result_ttl: 60 * 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde", default)]
+pub struct AppConfig {
+    pub service_version: String,
+    pub metrics_port: u16,
+    /// Bearer token the `/admin` endpoints of the metrics server require, they are
+    /// disabled without one.
+    pub admin_token: Option<String>,
+    pub worker: WorkerConfig,
+    /// Directory scanned for zksolc, zkvyper and solc binaries.
+    pub compilers_dir: PathBuf,
+    /// zksolc versions installed from the compiler mirror at startup, and offered while
+    /// `compilers_dir` holds no zksolc binary.
+    pub zksolc_versions: Vec<String>,
+    pub compiler_cache: CompilerCacheConfig,
+    pub zksolc_policy: ZksolcPolicyConfig,
+    /// Networks contracts can be verified on, all of them rendered into the hardhat config.
+    /// The first one is hardhat's default network.
+    pub networks: Vec<NetworkConfig>,
+    pub verification: VerificationConfig,
+    pub logging: LoggingConfig,
+    pub client_ip: ClientIpConfig,
+    pub cors: CorsConfig,
+    pub rate_limiter: RateLimiterConfig,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            service_version: "unknown".to_string(),
+            metrics_port: 8001,
+            admin_token: None,
+            worker: WorkerConfig::default(),
+            compilers_dir: PathBuf::from(COMPILERS_ROOT),
+            zksolc_versions: ["1.5.6", "1.5.5", "1.4.1", "1.4.0"]
+                .map(String::from)
+                .to_vec(),
+            compiler_cache: CompilerCacheConfig::default(),
+            zksolc_policy: ZksolcPolicyConfig::default(),
+            networks: default_networks(),
+            verification: VerificationConfig::default(),
+            logging: LoggingConfig::default(),
+            client_ip: ClientIpConfig::default(),
+            cors: CorsConfig::default(),
+            rate_limiter: RateLimiterConfig::default(),
+        }
+    }
+}
+
+impl AppConfig {
+    /// Reads the configuration from `Rocket.toml` (or the file in `ROCKET_CONFIG`) and the
+    /// `ROCKET_` prefixed env variables, the legacy variables in [`ENV_OVERRIDES`] taking
+    /// precedence.
+    pub fn load() -> Result<Self, CoreError> {
+        let overrides = Env::raw()
+            .only(&ENV_OVERRIDES.map(|(env, _)| env))
+            .map(|env| {
+                ENV_OVERRIDES
+                    .iter()
+                    .find(|(name, _)| env == *name)
+                    .map_or(env.into(), |(_, key)| (*key).into())
+            })
+            .global();
+
+        let config: AppConfig = Config::figment().merge(overrides).extract()?;
+        config.validate().map_err(CoreError::InvalidConfig)?;
+
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.worker.threads == 0 {
+            return Err("worker.threads must be at least 1".to_string());
+        }
+        if self.worker.queue_size == 0 {
+            return Err("worker.queue_size must be at least 1".to_string());
+        }
+        if self.worker.spawn_limit == 0 {
+            return Err("worker.spawn_limit must be at least 1".to_string());
+        }
+        if self.worker.result_ttl == 0 {
+            return Err("worker.result_ttl must be at least 1 second".to_string());
+        }
+
+        validate_networks(&self.networks)?;
+        self.verification.validate()?;
+        self.zksolc_policy.validate()?;
+        self.rate_limiter.validate()
+    }
+
+    pub fn network(&self, name: &str) -> Option<&NetworkConfig> {
+        self.networks.iter().find(|network| network.name == name)
+    }
+
+    /// The configuration as JSON, with the values of secret looking keys hidden.
+    pub fn redacted(&self) -> Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        redact(&mut value);
+        value
+    }
+}
+
+/// Whether `key` names a secret, e.g. `api_key` or `admin_tokens` but not `key_prefix`.
+fn is_secret_key(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    let last_segment = key.rsplit(['_', '-']).next().unwrap_or_default();
+
+    REDACTED_KEY_SEGMENTS.contains(&last_segment.trim_end_matches('s'))
+}
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if is_secret_key(key) {
+                    *value = Value::String(REDACTED_VALUE.to_string());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// Makes `config` the process wide configuration. Has to happen before anything reads it,
+/// otherwise the defaults are already in use.
+pub fn init_app_config(config: AppConfig) -> Result<(), CoreError> {
+    APP_CONFIG
+        .set(config)
+        .map_err(|_| CoreError::InvalidConfig("configuration was already initialized".to_string()))
+}
+
+pub fn app_config() -> &'static AppConfig {
+    APP_CONFIG.get().expect("configuration not initialized")
+}
+
+/// Initializes the default configuration for tests reading it.
+#[cfg(test)]
+pub fn init_test_app_config() {
+    APP_CONFIG.get_or_init(AppConfig::default);
+}
+
+/// Request guard of the admin endpoints, passing requests with an
+/// `Authorization: Bearer <admin_token>` header.
+pub struct Admin;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Admin {
+    type Error = ApiError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let Some(expected) = app_config().admin_token.as_deref() else {
+            return Outcome::Error((Status::Forbidden, ApiError::AdminDisabled));
+        };
+        let token = request
+            .headers()
+            .get_one("Authorization")
+            .and_then(|value| value.strip_prefix("Bearer "));
+
+        match token {
+            Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => {
+                Outcome::Success(Admin)
+            }
+            _ => Outcome::Error((Status::Unauthorized, ApiError::InvalidAdminToken)),
+        }
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[get("/admin/config")]
+pub fn admin_config(_admin: Admin) -> Json<Value> {
+    Json(app_config().redacted())
+}
+
+#[test]
+fn test_redacted_config() {
+    let mut value = serde_json::json!({
+        "worker": { "threads": 2 },
+        "verification": [{ "api_key": "abc", "url": "https://explorer" }],
+        "admin_token": "def",
+        "cache_key_prefix": "zk",
+        "monkey": 1,
+    });
+    redact(&mut value);
+
+    assert_eq!(value["worker"]["threads"], 2);
+    assert_eq!(value["verification"][0]["api_key"], REDACTED_VALUE);
+    assert_eq!(value["verification"][0]["url"], "https://explorer");
+    assert_eq!(value["admin_token"], REDACTED_VALUE);
+    assert_eq!(value["cache_key_prefix"], "zk");
+    assert_eq!(value["monkey"], 1);
+}
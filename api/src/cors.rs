@@ -1,10 +1,93 @@
 use rocket::fairing::{Fairing, Info, Kind};
 use rocket::http::{Header, Method, Status};
 use rocket::{Request, Response};
-use std::env;
+use serde::{Deserialize, Serialize};
 
-#[derive(Default)]
-pub struct CORS;
+use crate::rate_limiter::RATE_LIMIT_HEADERS;
+use crate::request_id::REQUEST_ID_HEADER;
+
+const DEFAULT_ALLOWED_ORIGIN: &str = "https://zksync-plugin.nethermind.dev";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde", default)]
+pub struct CorsConfig {
+    /// Exact origins or patterns where `*` matches any sequence of characters,
+    /// e.g. `https://*.nethermind.dev` or `http://localhost:*`.
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+    /// Seconds a browser may cache the preflight response.
+    pub max_age: u64,
+    pub allow_credentials: bool,
+    /// Plugin App URL, allowed in addition to `allowed_origins`.
+    pub vite_url: Option<String>,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: vec![DEFAULT_ALLOWED_ORIGIN.to_string()],
+            allowed_methods: vec!["GET".into(), "POST".into(), "OPTIONS".into()],
+            allowed_headers: vec![
+                "Accept".into(),
+                "Content-Type".into(),
+                REQUEST_ID_HEADER.into(),
+            ],
+            max_age: 60 * 60,
+            allow_credentials: true,
+            vite_url: None,
+        }
+    }
+}
+
+/// Matches `origin` against `pattern`, where `*` matches any (possibly empty) sequence.
+fn origin_matches(pattern: &str, origin: &str) -> bool {
+    let pattern = pattern.trim_end_matches('/').to_ascii_lowercase();
+    let origin = origin.to_ascii_lowercase();
+
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = origin.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No wildcard, the whole origin has to match
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.ends_with(last)
+}
+
+pub struct CORS {
+    config: CorsConfig,
+}
+
+impl CORS {
+    pub fn new(mut config: CorsConfig) -> Self {
+        if let Some(url) = &config.vite_url {
+            let origin = url.trim_end_matches('/').to_string();
+            config.allowed_origins.push(origin);
+        }
+
+        Self { config }
+    }
+
+    fn is_allowed(&self, origin: &str) -> bool {
+        self.config
+            .allowed_origins
+            .iter()
+            .any(|pattern| origin_matches(pattern, origin))
+    }
+}
 
 #[rocket::async_trait]
 impl Fairing for CORS {
@@ -16,28 +99,73 @@ impl Fairing for CORS {
     }
 
     async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
-        if request.method() == Method::Options {
-            response.set_status(Status::NoContent);
+        let is_preflight = request.method() == Method::Options;
+        if is_preflight {
             response.set_header(Header::new(
                 "Access-Control-Allow-Methods",
-                "POST, PATCH, GET, DELETE",
+                self.config.allowed_methods.join(", "),
+            ));
+            response.set_header(Header::new(
+                "Access-Control-Allow-Headers",
+                self.config.allowed_headers.join(", "),
+            ));
+            response.set_header(Header::new(
+                "Access-Control-Max-Age",
+                self.config.max_age.to_string(),
             ));
-            response.set_header(Header::new("Access-Control-Allow-Headers", "*"));
         }
 
-        // Take the Plugin App URL from the env variable, if set
-        match env::var("VITE_URL") {
-            Ok(v) => {
-                response.set_header(Header::new("Access-Control-Allow-Origin", v));
-            }
-            Err(_) => {
-                response.set_header(Header::new(
-                    "Access-Control-Allow-Origin",
-                    "https://zksync-plugin.nethermind.dev/",
-                ));
-            }
+        // Responses differ per requesting origin, caches must key on it
+        response.adjoin_header(Header::new("Vary", "Origin"));
+
+        let origin = match request.headers().get_one("Origin") {
+            Some(origin) if self.is_allowed(origin) => origin.to_string(),
+            _ => return,
+        };
+
+        if is_preflight {
+            response.set_status(Status::NoContent);
         }
 
-        response.set_header(Header::new("Access-Control-Allow-Credentials", "true"));
+        response.set_header(Header::new("Access-Control-Allow-Origin", origin));
+        if self.config.allow_credentials {
+            response.set_header(Header::new("Access-Control-Allow-Credentials", "true"));
+        }
+        let exposed_headers: Vec<&str> = RATE_LIMIT_HEADERS
+            .into_iter()
+            .chain([REQUEST_ID_HEADER])
+            .collect();
+        response.set_header(Header::new(
+            "Access-Control-Expose-Headers",
+            exposed_headers.join(", "),
+        ));
     }
 }
+
+#[test]
+fn test_origin_patterns() {
+    assert!(origin_matches(
+        "https://zksync-plugin.nethermind.dev/",
+        "https://zksync-plugin.nethermind.dev"
+    ));
+    assert!(!origin_matches(
+        "https://zksync-plugin.nethermind.dev",
+        "https://zksync-plugin.nethermind.dev.evil.com"
+    ));
+    assert!(origin_matches(
+        "https://*.nethermind.dev",
+        "https://pr-42.preview.nethermind.dev"
+    ));
+    assert!(!origin_matches(
+        "https://*.nethermind.dev",
+        "https://nethermind.dev.evil.com"
+    ));
+    assert!(origin_matches(
+        "http://localhost:*",
+        "http://localhost:3000"
+    ));
+    assert!(!origin_matches(
+        "http://localhost:*",
+        "https://localhost:3000"
+    ));
+}
@@ -1,3 +1,4 @@
+use rocket::serde::json::serde_json;
 use std::io::Error as IoError;
 
 #[derive(Debug, thiserror::Error)]
@@ -30,6 +31,10 @@ pub enum ApiError {
     FailedToGetClientIp,
     #[error("Too many requests")]
     TooManyRequests,
+    #[error("The admin endpoints are disabled, no admin token is configured")]
+    AdminDisabled,
+    #[error("Missing or invalid admin token")]
+    InvalidAdminToken,
     #[error("Error while trying to unlock mutex")]
     MutexUnlockError,
     #[error("Error('s) raised while trying to parse sol file: \n{0}")]
@@ -38,6 +43,10 @@ pub enum ApiError {
     FailedToWriteFile(IoError),
     #[error("Unsupported version: {0}")]
     VersionNotSupported(String),
+    #[error("Invalid solc version {0}, expected x.y.z")]
+    InvalidSolcVersion(String),
+    #[error("solc {0} is not supported by zksolc {1}")]
+    SolcVersionNotSupported(String, String),
     #[error("Failed to get parent directory")]
     FailedToGetParentDir,
     #[error("Failed to remove directory")]
@@ -46,16 +55,104 @@ pub enum ApiError {
     FailedToRemoveFile(IoError),
     #[error("Unknown network selected: {0}")]
     UnknownNetwork(String),
+    #[error("Invalid library {0}, expected path/Lib.sol:Lib=0x…")]
+    InvalidLibrary(String),
+    #[error("The batch returned no result for {0}")]
+    MissingBatchResult(String),
+    #[error("The batch lists no contracts to verify")]
+    EmptyBatch,
+    #[error("The batch lists {0} contracts, at most {1} are allowed")]
+    BatchTooLarge(usize, usize),
+    #[error("Compilation {0} not found, it may have expired")]
+    CompilationNotFound(String),
+    #[error("Network {0} has no {1} verifier")]
+    NoVerifier(String, &'static str),
+    #[error(transparent)]
+    VerificationError(#[from] VerificationError),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum VerificationError {
+    #[error("The contract to verify has to be given as path/Some.sol:ContractName")]
+    MissingTargetContract,
+    #[error("Failed to reach the verifier: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("Verifier rejected the request ({0}): {1}")]
+    Rejected(u16, String),
+    #[error("Unexpected verifier response: {0}")]
+    InvalidResponse(String),
+    #[error("solc {0} is not a known release")]
+    UnknownSolcVersion(String),
+    #[error("Invalid verification id {0}")]
+    InvalidVerificationId(String),
+    #[error("Verification {0} did not finish in time")]
+    Timeout(String),
+    #[error("Invalid constructor arguments: {0}")]
+    InvalidConstructorArgs(String),
+    #[error("The contract ABI is needed to convert the constructor arguments")]
+    ConstructorAbiRequired,
+    #[error("RPC request failed: {0}")]
+    Rpc(String),
+    #[error("No compiled artifact for {0}")]
+    ArtifactNotFound(String),
+    #[error("Unexpected result of the proxy's {0}: {1}")]
+    InvalidProxy(String, String),
+    #[error("Invalid deployed bytecode: {0}")]
+    InvalidBytecode(String),
+    #[error("Neither an EIP-1967 implementation nor beacon is set at {0}, is it a proxy?")]
+    NotAProxy(String),
+    #[error("Failed to compile the sources for the bytecode check: {0}")]
+    CompilationFailed(String),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CompilerCacheError {
+    #[error("Failed to read compiler manifest: {0}")]
+    ManifestRead(IoError),
+    #[error("Failed to parse compiler manifest: {0}")]
+    ManifestParse(serde_json::Error),
+    #[error("{0} {1} is not in the compiler manifest")]
+    NotInManifest(&'static str, String),
+    #[error("Checksum mismatch for {file:?}: expected {expected}, got {actual}")]
+    ChecksumMismatch {
+        file: std::path::PathBuf,
+        expected: String,
+        actual: String,
+    },
+    #[error(transparent)]
+    Io(IoError),
 }
 
 #[derive(thiserror::Error, Debug)]
 pub enum CoreError {
     #[error(transparent)]
-    RocketError(#[from] rocket::Error),
+    RocketError(Box<rocket::Error>),
+    #[error(transparent)]
+    ConfigError(Box<rocket::figment::Error>),
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(String),
     #[error(transparent)]
     PrometheusError(#[from] prometheus::Error),
     #[error(transparent)]
     LoggingGlobalError(#[from] tracing::dispatcher::SetGlobalDefaultError),
+    #[error("Failed to open log file: {0}")]
+    LogFileError(#[from] tracing_appender::rolling::InitError),
+    #[error(transparent)]
+    TracingExporterError(#[from] opentelemetry_otlp::ExporterBuildError),
+    #[error(transparent)]
+    CompilerCacheError(#[from] CompilerCacheError),
+}
+
+impl From<rocket::Error> for CoreError {
+    fn from(err: rocket::Error) -> Self {
+        CoreError::RocketError(Box::new(err))
+    }
+}
+
+impl From<rocket::figment::Error> for CoreError {
+    fn from(err: rocket::figment::Error) -> Self {
+        CoreError::ConfigError(Box::new(err))
+    }
 }
 
 pub type Result<T, E = ApiError> = std::result::Result<T, E>;
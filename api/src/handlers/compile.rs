@@ -1,16 +1,21 @@
+use crate::client_ip::ClientIp;
+use crate::compilation_store::compilation_store;
+use crate::compiler_registry::{compiler_registry, CompilerKind};
 use crate::errors::{ApiError, Result};
 use crate::handlers::process::{do_process_command, fetch_process_result};
 use crate::handlers::types::{
     ApiCommand, ApiCommandResult, CompilationRequest, CompileResponse, CompiledFile,
 };
-use crate::handlers::SPAWN_SEMAPHORE;
+use crate::handlers::{observe_action, SPAWN_SEMAPHORE};
 use crate::metrics::Metrics;
 use crate::rate_limiter::RateLimited;
+use crate::request_id::RequestId;
 use crate::utils::cleaner::AutoCleanUp;
 use crate::utils::hardhat_config::HardhatConfigBuilder;
+use crate::utils::job_log::{wait_with_captured_output, JobLog};
 use crate::utils::lib::{
     generate_folder_name, initialize_files, list_files_in_directory, status_code_to_message,
-    DEFAULT_SOLIDITY_VERSION, SOL_ROOT, ZKSOLC_VERSIONS,
+    SOL_ROOT,
 };
 use crate::worker::WorkerEngine;
 use rocket::serde::json;
@@ -18,46 +23,77 @@ use rocket::serde::json::Json;
 use rocket::{tokio, State};
 use std::path::Path;
 use std::process::Stdio;
-use tracing::instrument;
+use std::time::Instant;
 use tracing::{error, info};
+use tracing::{info_span, instrument, Instrument};
 
 pub(crate) const COMPILATION_LABEL_VALUE: &str = "compilation";
 
-#[instrument(skip(request_json, _rate_limited, engine))]
+#[instrument(
+    skip(request_json, _rate_limited, request_id, client_ip, engine),
+    fields(%request_id, client = %client_ip.tag())
+)]
 #[post("/compile", format = "json", data = "<request_json>")]
 pub async fn compile(
     request_json: Json<CompilationRequest>,
     _rate_limited: RateLimited,
+    request_id: RequestId,
+    client_ip: ClientIp,
     engine: &State<WorkerEngine>,
 ) -> Json<CompileResponse> {
     info!("/compile/{:?}", request_json.config);
 
-    do_compile(request_json.0, &engine.metrics, false)
-        .await
-        .unwrap_or_else(|e| {
-            Json(CompileResponse {
-                file_content: vec![],
-                message: e.to_string(),
-                status: "Error".to_string(),
-            })
+    let zksolc_version = request_json.config.version.clone();
+    let start_time = Instant::now();
+    let result = do_compile(request_json.0, &engine.metrics, CompileMode::User, None).await;
+    observe_action(
+        &engine.metrics,
+        COMPILATION_LABEL_VALUE,
+        &zksolc_version,
+        result.as_ref().map(|response| response.status.as_str()),
+        None,
+        start_time.elapsed(),
+    );
+
+    let response = result.unwrap_or_else(|e| {
+        Json(CompileResponse {
+            file_content: vec![],
+            message: e.to_string(),
+            status: "Error".to_string(),
+            warnings: vec![],
+            compilation_id: None,
+            request_id: None,
         })
+    });
+
+    Json(response.into_inner().tag_failure(&request_id.0))
 }
 
-#[instrument(skip(request_json, _rate_limited, engine))]
+#[instrument(
+    skip(request_json, _rate_limited, request_id, client_ip, engine),
+    fields(%request_id, client = %client_ip.tag())
+)]
 #[post("/compile-async", format = "json", data = "<request_json>")]
 pub async fn compile_async(
     request_json: Json<CompilationRequest>,
     _rate_limited: RateLimited,
+    request_id: RequestId,
+    client_ip: ClientIp,
     engine: &State<WorkerEngine>,
 ) -> String {
     info!("/compile-async/{:?}", request_json.config);
 
-    do_process_command(ApiCommand::Compile(request_json.0), engine)
+    do_process_command(ApiCommand::Compile(request_json.0), &request_id, engine)
 }
 
-#[instrument(skip(engine))]
+#[instrument(skip(request_id, client_ip, engine), fields(%request_id, client = %client_ip.tag()))]
 #[get("/compile-result/<process_id>")]
-pub async fn get_compile_result(process_id: String, engine: &State<WorkerEngine>) -> String {
+pub async fn get_compile_result(
+    process_id: String,
+    request_id: RequestId,
+    client_ip: ClientIp,
+    engine: &State<WorkerEngine>,
+) -> String {
     info!("/compile-result/{:?}", process_id);
 
     fetch_process_result(process_id, engine, |result| match result {
@@ -68,23 +104,47 @@ pub async fn get_compile_result(process_id: String, engine: &State<WorkerEngine>
     })
 }
 
+/// Who a compilation runs for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompileMode {
+    /// Requested by a user, counted in the metrics and stored for later verification.
+    User,
+    /// Run by the service itself, for the health check or a verification's bytecode check.
+    Internal,
+}
+
+#[instrument(skip_all)]
 pub async fn do_compile(
     compilation_request: CompilationRequest,
     metrics: &Metrics,
-    is_health_check: bool,
+    mode: CompileMode,
+    job_log: Option<&JobLog>,
 ) -> Result<Json<CompileResponse>> {
-    let zksolc_version = compilation_request.config.version;
+    let is_user_compilation = mode == CompileMode::User;
+    // kept for verifying the compiled contracts later on
+    let stored_request = is_user_compilation.then(|| compilation_request.clone());
+    let zksolc_version = compilation_request.config.version.clone();
 
     // check if the version is supported
-    if !ZKSOLC_VERSIONS.contains(&zksolc_version.as_str()) {
+    if !compiler_registry().is_supported(CompilerKind::Zksolc, &zksolc_version) {
         return Err(ApiError::VersionNotSupported(zksolc_version));
     }
+    if let Some(solc_version) = &compilation_request.config.solc_version {
+        compiler_registry().check_solc_version(&zksolc_version, solc_version)?;
+    }
+    let warnings: Vec<String> = compiler_registry()
+        .deprecation_warning(&zksolc_version)
+        .into_iter()
+        .collect();
 
     if compilation_request.contracts.is_empty() {
         return Ok(Json(CompileResponse {
             file_content: vec![],
             status: status_code_to_message(Some(0)),
             message: "Nothing to compile".into(),
+            warnings,
+            compilation_id: None,
+            request_id: None,
         }));
     }
 
@@ -102,65 +162,67 @@ pub async fn do_compile(
     let user_files_path_str = workspace_path_str.clone();
     let hardhat_config_path = Path::new(&user_files_path_str).join("hardhat.config.ts");
 
-    // instantly create the directories
-    tokio::fs::create_dir_all(workspace_path)
-        .await
-        .map_err(ApiError::FailedToWriteFile)?;
-    tokio::fs::create_dir_all(artifacts_path)
-        .await
-        .map_err(ApiError::FailedToWriteFile)?;
-
     // when the compilation is done, clean up the directories
     // it will be called when the AutoCleanUp struct is dropped
     let auto_clean_up = AutoCleanUp {
         dirs: vec![workspace_path.to_str().unwrap()],
     };
 
-    // write the hardhat config file
-    let mut hardhat_config_builder = HardhatConfigBuilder::new();
-    hardhat_config_builder
-        .zksolc_version(&zksolc_version)
-        .solidity_version(DEFAULT_SOLIDITY_VERSION);
-    if let Some(target_path) = compilation_request.target_path {
-        hardhat_config_builder.paths_sources(&target_path);
-    }
-
-    let hardhat_config_content = hardhat_config_builder.build().to_string_config();
-
-    // create parent directories
-    tokio::fs::create_dir_all(hardhat_config_path.parent().unwrap())
-        .await
-        .map_err(ApiError::FailedToWriteFile)?;
+    async {
+        // instantly create the directories
+        tokio::fs::create_dir_all(workspace_path)
+            .await
+            .map_err(ApiError::FailedToWriteFile)?;
+        tokio::fs::create_dir_all(artifacts_path)
+            .await
+            .map_err(ApiError::FailedToWriteFile)?;
+
+        // write the hardhat config file
+        let hardhat_config_content = HardhatConfigBuilder::from_compilation(
+            &compilation_request.config,
+            compilation_request.target_path.as_deref(),
+        )?
+        .build()
+        .to_string_config();
+
+        // create parent directories
+        tokio::fs::create_dir_all(hardhat_config_path.parent().unwrap())
+            .await
+            .map_err(ApiError::FailedToWriteFile)?;
 
-    tokio::fs::write(hardhat_config_path, hardhat_config_content)
-        .await
-        .map_err(ApiError::FailedToWriteFile)?;
+        tokio::fs::write(hardhat_config_path, hardhat_config_content)
+            .await
+            .map_err(ApiError::FailedToWriteFile)?;
 
-    // filter test files from compilation candidates
-    let contracts = compilation_request
-        .contracts
-        .into_iter()
-        .filter(|contract| !contract.file_name.ends_with("_test.sol"))
-        .collect();
+        // filter test files from compilation candidates
+        let contracts = compilation_request
+            .contracts
+            .into_iter()
+            .filter(|contract| !contract.file_name.ends_with("_test.sol"))
+            .collect();
 
-    // initialize the files
-    initialize_files(contracts, workspace_path).await?;
-
-    // Limit number of spawned processes. RAII released
-    let _permit = SPAWN_SEMAPHORE.acquire().await.expect("Expired semaphore");
-
-    let command = tokio::process::Command::new("npx")
-        .arg("hardhat")
-        .arg("compile")
-        .current_dir(workspace_path)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn();
-    let process = command.map_err(ApiError::FailedToExecuteCommand)?;
-    let output = process
-        .wait_with_output()
-        .await
-        .map_err(ApiError::FailedToReadOutput)?;
+        // initialize the files
+        initialize_files(contracts, workspace_path).await
+    }
+    .instrument(info_span!("workspace_setup"))
+    .await?;
+
+    let output = async {
+        // Limit number of spawned processes. RAII released
+        let _permit = SPAWN_SEMAPHORE.acquire().await.expect("Expired semaphore");
+
+        let command = tokio::process::Command::new("npx")
+            .arg("hardhat")
+            .arg("compile")
+            .current_dir(workspace_path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn();
+        let process = command.map_err(ApiError::FailedToExecuteCommand)?;
+        wait_with_captured_output(process, job_log).await
+    }
+    .instrument(info_span!("hardhat_spawn"))
+    .await?;
 
     let status = output.status;
     let message = String::from_utf8_lossy(&output.stdout).to_string();
@@ -172,7 +234,7 @@ pub async fn do_compile(
             String::from_utf8_lossy(&output.stderr)
         );
 
-        if !is_health_check {
+        if is_user_compilation {
             metrics
                 .action_failures_total
                 .with_label_values(&[COMPILATION_LABEL_VALUE])
@@ -186,45 +248,64 @@ pub async fn do_compile(
                 String::from_utf8_lossy(&output.stderr)
             ),
             status: "Error".to_string(),
+            warnings,
+            compilation_id: None,
+            request_id: None,
         }));
     }
 
     // fetch the files in the artifacts directory
-    let mut file_contents: Vec<CompiledFile> = vec![];
-    let file_paths = list_files_in_directory(artifacts_path);
+    let file_contents = async {
+        let mut file_contents: Vec<CompiledFile> = vec![];
+        let file_paths = list_files_in_directory(artifacts_path);
+
+        for file_path in file_paths.iter() {
+            let file_content = tokio::fs::read_to_string(file_path)
+                .await
+                .map_err(ApiError::FailedToReadFile)?;
+            let full_path = Path::new(file_path);
+            let relative_path = full_path.strip_prefix(artifacts_path).unwrap_or(full_path);
+            let relative_path_str = relative_path.to_str().unwrap();
+
+            // todo(varex83): is it the best way to check?
+            let is_contract =
+                !relative_path_str.ends_with(".dbg.json") && relative_path_str.ends_with(".json");
+
+            file_contents.push(CompiledFile {
+                file_name: relative_path_str.to_string(),
+                file_content,
+                is_contract,
+            });
+        }
 
-    for file_path in file_paths.iter() {
-        let file_content = tokio::fs::read_to_string(file_path)
-            .await
-            .map_err(ApiError::FailedToReadFile)?;
-        let full_path = Path::new(file_path);
-        let relative_path = full_path.strip_prefix(artifacts_path).unwrap_or(full_path);
-        let relative_path_str = relative_path.to_str().unwrap();
-
-        // todo(varex83): is it the best way to check?
-        let is_contract =
-            !relative_path_str.ends_with(".dbg.json") && relative_path_str.ends_with(".json");
-
-        file_contents.push(CompiledFile {
-            file_name: relative_path_str.to_string(),
-            file_content,
-            is_contract,
-        });
+        Ok::<_, ApiError>(file_contents)
     }
+    .instrument(info_span!("artifact_collection"))
+    .await?;
 
     // calling here explicitly to avoid dropping the AutoCleanUp struct
-    auto_clean_up.clean_up().await;
+    auto_clean_up
+        .clean_up()
+        .instrument(info_span!("cleanup"))
+        .await;
 
-    if !is_health_check {
+    if is_user_compilation {
         metrics
             .action_successes_total
             .with_label_values(&[COMPILATION_LABEL_VALUE])
             .inc();
     }
 
+    let compilation_id = stored_request
+        .and_then(|request| compilation_store().insert(request))
+        .map(|id| id.to_string());
+
     Ok(Json(CompileResponse {
         file_content: file_contents,
         status: status_code_to_message(status.code()),
         message,
+        warnings,
+        compilation_id,
+        request_id: None,
     }))
 }
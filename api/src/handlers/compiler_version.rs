@@ -1,28 +1,44 @@
+use crate::client_ip::ClientIp;
+use crate::compiler_registry::{compiler_registry, CompilerKind};
 use crate::errors::ApiError;
-use crate::utils::lib::ZKSOLC_VERSIONS;
+use crate::request_id::RequestId;
 use rocket::serde::json::serde_json;
 use tracing::{info, instrument};
 
-#[instrument]
+#[instrument(skip(request_id, client_ip), fields(%request_id, client = %client_ip.tag()))]
 #[get("/compiler_version")]
-pub async fn compiler_version() -> String {
+pub async fn compiler_version(request_id: RequestId, client_ip: ClientIp) -> String {
     info!("/compiler_version");
     do_compiler_version().unwrap_or_else(|e| e.to_string())
 }
 
-#[instrument]
+#[instrument(skip(request_id, client_ip), fields(%request_id, client = %client_ip.tag()))]
 #[get("/allowed_versions")]
-pub async fn allowed_versions() -> String {
+pub async fn allowed_versions(request_id: RequestId, client_ip: ClientIp) -> String {
     info!("/allowed_versions");
     do_allowed_versions().unwrap_or_else(|e| format!("Error: {:?}", e))
 }
 
-/// Run ./zksolc --version to return compiler version string
-///
+#[instrument(skip(request_id, client_ip), fields(%request_id, client = %client_ip.tag()))]
+#[get("/versions")]
+pub async fn versions(request_id: RequestId, client_ip: ClientIp) -> String {
+    info!("/versions");
+    do_versions().unwrap_or_else(|e| format!("Error: {:?}", e))
+}
+
+/// Newest zksolc known to the compiler registry, e.g. `zksolc-1.5.6`
 pub fn do_compiler_version() -> Result<String, ApiError> {
-    Ok("zksolc-latest".to_string())
+    compiler_registry()
+        .versions(CompilerKind::Zksolc)
+        .first()
+        .map(|version| format!("zksolc-{}", version))
+        .ok_or_else(|| ApiError::VersionNotSupported("zksolc".to_string()))
 }
 
 pub fn do_allowed_versions() -> Result<String, ApiError> {
-    Ok(serde_json::to_string(&ZKSOLC_VERSIONS).unwrap())
+    Ok(serde_json::to_string(&compiler_registry().versions(CompilerKind::Zksolc)).unwrap())
+}
+
+pub fn do_versions() -> Result<String, ApiError> {
+    Ok(serde_json::to_string(&compiler_registry().zksolc_versions_info()).unwrap())
 }
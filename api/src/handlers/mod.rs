@@ -4,34 +4,56 @@ pub mod process;
 pub mod types;
 pub mod utils;
 pub mod verify;
+pub mod verify_batch;
+pub mod verify_proxy;
 
 use lazy_static::lazy_static;
 use rocket::State;
+use std::time::Duration;
 use tokio::sync::Semaphore;
 use tokio::time::Instant;
 use tracing::info;
 use tracing::instrument;
 
+use crate::client_ip::ClientIp;
+use crate::compiler_registry::{compiler_registry, CompilerKind};
+use crate::config::app_config;
 use crate::errors::ApiError;
-use crate::handlers::compile::{do_compile, COMPILATION_LABEL_VALUE};
+use crate::handlers::compile::{do_compile, CompileMode, COMPILATION_LABEL_VALUE};
 use crate::handlers::compiler_version::do_compiler_version;
 use crate::handlers::types::{ApiCommand, ApiCommandResult, HealthCheckResponse};
 use crate::handlers::verify::{do_verify, VERIFICATION_LABEL_VALUE};
+use crate::handlers::verify_batch::do_verify_batch;
 use crate::metrics::Metrics;
+use crate::request_id::RequestId;
+use crate::utils::job_log::JobLog;
 use crate::utils::lib::generate_mock_compile_request;
 use crate::worker::WorkerEngine;
 
-const PROCESS_SPAWN_LIMIT: usize = 8;
 lazy_static! {
-    static ref SPAWN_SEMAPHORE: Semaphore = Semaphore::new(PROCESS_SPAWN_LIMIT);
+    pub(crate) static ref SPAWN_SEMAPHORE: Semaphore =
+        Semaphore::new(app_config().worker.spawn_limit);
 }
 
-#[instrument(skip(engine))]
+#[instrument(skip(request_id, client_ip, engine), fields(%request_id, client = %client_ip.tag()))]
 #[get("/health")]
-pub async fn health(engine: &State<WorkerEngine>) -> HealthCheckResponse {
+pub async fn health(
+    request_id: RequestId,
+    client_ip: ClientIp,
+    engine: &State<WorkerEngine>,
+) -> HealthCheckResponse {
     info!("/health");
 
-    let result = do_compile(generate_mock_compile_request(), &engine.metrics, true).await;
+    let Some(zksolc_version) = compiler_registry().default_zksolc_version() else {
+        return HealthCheckResponse::error("No zksolc version available");
+    };
+    let result = do_compile(
+        generate_mock_compile_request(&zksolc_version),
+        &engine.metrics,
+        CompileMode::Internal,
+        None,
+    )
+    .await;
 
     if result.is_ok() {
         HealthCheckResponse::ok()
@@ -40,16 +62,44 @@ pub async fn health(engine: &State<WorkerEngine>) -> HealthCheckResponse {
     }
 }
 
-#[instrument]
+#[instrument(skip(request_id, client_ip), fields(%request_id, client = %client_ip.tag()))]
 #[get("/")]
-pub async fn who_is_this() -> &'static str {
+pub async fn who_is_this(request_id: RequestId, client_ip: ClientIp) -> &'static str {
     info!("/who_is_this");
     "Who are you?"
 }
 
+/// Records the latency histograms of a compile or verify action. `status` is the status of
+/// the produced response, or the error if none was produced.
+pub(crate) fn observe_action(
+    metrics: &Metrics,
+    action: &str,
+    zksolc_version: &str,
+    status: Result<&str, &ApiError>,
+    queue_wait: Option<Duration>,
+    execution: Duration,
+) {
+    // Versions come from the request, keep unknown ones from creating new series
+    let zksolc_version = if compiler_registry().is_supported(CompilerKind::Zksolc, zksolc_version) {
+        zksolc_version
+    } else {
+        "unsupported"
+    };
+    let outcome = match status {
+        Ok("Success") => "success",
+        Ok(_) => "failure",
+        Err(_) => "error",
+    };
+
+    metrics.observe_action(&[action, zksolc_version, outcome], queue_wait, execution);
+}
+
 pub async fn dispatch_command(
     command: ApiCommand,
     metrics: &Metrics,
+    queue_wait: Duration,
+    request_id: &str,
+    job_log: &JobLog,
 ) -> Result<ApiCommandResult, ApiError> {
     let start_time = Instant::now();
 
@@ -59,10 +109,11 @@ pub async fn dispatch_command(
             Err(e) => Err(e),
         },
         ApiCommand::Compile(request) => {
-            let res = match do_compile(request, metrics, false).await {
-                Ok(compile_response) => {
-                    Ok(ApiCommandResult::Compile(compile_response.into_inner()))
-                }
+            let zksolc_version = request.config.version.clone();
+            let res = match do_compile(request, metrics, CompileMode::User, Some(job_log)).await {
+                Ok(compile_response) => Ok(ApiCommandResult::Compile(
+                    compile_response.into_inner().tag_failure(request_id),
+                )),
                 Err(e) => {
                     metrics
                         .action_failures_total
@@ -72,17 +123,61 @@ pub async fn dispatch_command(
                 }
             };
 
-            let elapsed_time = start_time.elapsed().as_secs_f64();
-            metrics
-                .action_duration_seconds
-                .with_label_values(&[COMPILATION_LABEL_VALUE])
-                .set(elapsed_time);
+            let status = match &res {
+                Ok(ApiCommandResult::Compile(response)) => Ok(response.status.as_str()),
+                Ok(_) => Ok("Success"),
+                Err(e) => Err(e),
+            };
+            observe_action(
+                metrics,
+                COMPILATION_LABEL_VALUE,
+                &zksolc_version,
+                status,
+                Some(queue_wait),
+                start_time.elapsed(),
+            );
 
             res
         }
         ApiCommand::Verify(request) => {
-            let res = match do_verify(request, metrics).await {
-                Ok(verify_response) => Ok(ApiCommandResult::Verify(verify_response.into_inner())),
+            let zksolc_version = request.config.zksolc_version.clone();
+            let res = match do_verify(*request, metrics, Some(job_log)).await {
+                Ok(verify_response) => Ok(ApiCommandResult::Verify(
+                    verify_response.into_inner().tag_failure(request_id),
+                )),
+                Err(e) => {
+                    metrics
+                        .action_failures_total
+                        .with_label_values(&[VERIFICATION_LABEL_VALUE])
+                        .inc();
+
+                    Err(e)
+                }
+            };
+
+            let status = match &res {
+                Ok(ApiCommandResult::Verify(response)) => Ok(response.status.as_str()),
+                Ok(_) => Ok("Success"),
+                Err(e) => Err(e),
+            };
+            observe_action(
+                metrics,
+                VERIFICATION_LABEL_VALUE,
+                &zksolc_version,
+                status,
+                Some(queue_wait),
+                start_time.elapsed(),
+            );
+
+            res
+        }
+        ApiCommand::VerifyBatch(request) => {
+            let zksolc_version = request.config.zksolc_version.clone();
+            // the contracts are counted one by one, only errors of the whole batch are left
+            let res = match do_verify_batch(*request, metrics, Some(job_log)).await {
+                Ok(verify_response) => Ok(ApiCommandResult::VerifyBatch(
+                    verify_response.into_inner().tag_failure(request_id),
+                )),
                 Err(e) => {
                     metrics
                         .action_failures_total
@@ -93,11 +188,19 @@ pub async fn dispatch_command(
                 }
             };
 
-            let elapsed_time = start_time.elapsed().as_secs_f64();
-            metrics
-                .action_duration_seconds
-                .with_label_values(&[VERIFICATION_LABEL_VALUE])
-                .set(elapsed_time);
+            let status = match &res {
+                Ok(ApiCommandResult::VerifyBatch(response)) => Ok(response.status.as_str()),
+                Ok(_) => Ok("Success"),
+                Err(e) => Err(e),
+            };
+            observe_action(
+                metrics,
+                VERIFICATION_LABEL_VALUE,
+                &zksolc_version,
+                status,
+                Some(queue_wait),
+                start_time.elapsed(),
+            );
 
             res
         }
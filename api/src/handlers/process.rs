@@ -1,12 +1,20 @@
+use crate::client_ip::ClientIp;
 use crate::handlers::types::{ApiCommand, ApiCommandResult};
+use crate::request_id::RequestId;
 use crate::worker::{ProcessState, WorkerEngine};
+use rocket::serde::json;
 use rocket::State;
 use tracing::{info, instrument};
 use uuid::Uuid;
 
-#[instrument(skip(engine))]
+#[instrument(skip(engine, request_id, client_ip), fields(%request_id, client = %client_ip.tag()))]
 #[get("/process_status/<process_id>")]
-pub async fn get_process_status(process_id: String, engine: &State<WorkerEngine>) -> String {
+pub async fn get_process_status(
+    process_id: String,
+    request_id: RequestId,
+    client_ip: ClientIp,
+    engine: &State<WorkerEngine>,
+) -> String {
     info!("/process_status/{:?}", process_id);
     // get status of process by ID
     match Uuid::parse_str(&process_id) {
@@ -21,9 +29,36 @@ pub async fn get_process_status(process_id: String, engine: &State<WorkerEngine>
     }
 }
 
-pub fn do_process_command(command: ApiCommand, engine: &State<WorkerEngine>) -> String {
+#[instrument(skip(engine, request_id, client_ip), fields(%request_id, client = %client_ip.tag()))]
+#[get("/process/<process_id>/logs?<offset>&<tail>")]
+pub async fn get_process_logs(
+    process_id: String,
+    offset: Option<usize>,
+    tail: Option<usize>,
+    request_id: RequestId,
+    client_ip: ClientIp,
+    engine: &State<WorkerEngine>,
+) -> String {
+    info!("/process/{:?}/logs", process_id);
+
+    match Uuid::parse_str(&process_id) {
+        Ok(process_uuid) => match engine.arc_process_states.get(&process_uuid) {
+            Some(entry) => {
+                json::to_string(&entry.value().job_log.page(offset, tail)).unwrap_or_default()
+            }
+            None => "Process id not found".to_string(),
+        },
+        Err(e) => e.to_string(),
+    }
+}
+
+pub fn do_process_command(
+    command: ApiCommand,
+    request_id: &RequestId,
+    engine: &State<WorkerEngine>,
+) -> String {
     // queue the new Scarb command
-    match engine.enqueue_command(command) {
+    match engine.enqueue_command(command, request_id) {
         Ok(uuid) => {
             format!("{}", uuid)
         }
@@ -48,8 +83,9 @@ where
                     .get(&process_uuid)
                     .unwrap()
                     .value()
+                    .state
                 {
-                    ProcessState::Completed(result) => do_work(result),
+                    ProcessState::Completed(ref result) => do_work(result),
                     _ => "Result not available".to_string(),
                 }
             } else {
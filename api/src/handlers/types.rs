@@ -1,7 +1,16 @@
 use rocket::http::Status;
 use rocket::response::Responder;
+use rocket::serde::json::Value;
 use rocket::Request;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::errors::ApiError;
+use crate::verification::backend::VerifierKind;
+use crate::verification::bytecode::BytecodeComparison;
+use crate::verification::outcome::VerificationOutcome;
+use crate::verification::proxy::ProxyVerification;
+use crate::verification::OptimizerSettings;
 
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(crate = "rocket::serde")]
@@ -9,6 +18,25 @@ pub struct CompileResponse {
     pub status: String,
     pub message: String,
     pub file_content: Vec<CompiledFile>,
+    /// Non fatal notices about the compilation, e.g. the use of a deprecated zksolc.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// Id to verify the compiled contracts with, without uploading the sources again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compilation_id: Option<String>,
+    /// Set on failed compilations to correlate them with the server logs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+impl CompileResponse {
+    /// Attaches the id of the originating request to unsuccessful responses.
+    pub fn tag_failure(mut self, request_id: &str) -> Self {
+        if self.status != "Success" {
+            self.request_id = Some(request_id.to_string());
+        }
+        self
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -20,19 +48,89 @@ pub struct CompiledFile {
     pub is_contract: bool,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(crate = "rocket::serde")]
 pub struct VerifyResponse {
     pub status: String,
     pub message: String,
+    #[serde(flatten)]
+    pub outcome: VerificationOutcome,
+    /// Set when the local bytecode check found a mismatch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytecode_check: Option<BytecodeComparison>,
+    /// Set when verifying a proxy, links it to its implementation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<Box<ProxyVerification>>,
+    /// Set on failed verifications to correlate them with the server logs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+impl VerifyResponse {
+    pub fn new(outcome: VerificationOutcome, message: String) -> Self {
+        Self {
+            status: outcome.status().to_string(),
+            message,
+            outcome,
+            bytecode_check: None,
+            proxy: None,
+            request_id: None,
+        }
+    }
+
+    pub fn from_error(error: &ApiError) -> Self {
+        VerifyResponse::new(VerificationOutcome::from_error(error), error.to_string())
+    }
+
+    /// Attaches the id of the originating request to unsuccessful responses.
+    pub fn tag_failure(mut self, request_id: &str) -> Self {
+        if self.status != "Success" {
+            self.request_id = Some(request_id.to_string());
+        }
+        self
+    }
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 #[serde(crate = "rocket::serde")]
 pub struct CompilationConfig {
     pub version: String,
+    /// Deployed libraries to link against, as `path/Lib.sol:Lib=0x…`.
     #[serde(default)]
     pub user_libraries: Vec<String>,
+    #[serde(default)]
+    pub solc_version: Option<String>,
+    #[serde(default)]
+    pub optimizer: OptimizerSettings,
+}
+
+/// Library addresses by library name, by source file.
+pub type Libraries = BTreeMap<String, BTreeMap<String, String>>;
+
+impl CompilationConfig {
+    /// `user_libraries` the way zksolc and the standard JSON input take them.
+    pub fn libraries(&self) -> Result<Libraries, ApiError> {
+        let mut libraries = Libraries::new();
+        for library in &self.user_libraries {
+            let (path, name, address) = library
+                .split_once('=')
+                .and_then(|(path_name, address)| {
+                    let (path, name) = path_name.trim().rsplit_once(':')?;
+                    Some((path, name, address.trim()))
+                })
+                .filter(|(path, name, address)| {
+                    !path.is_empty() && !name.is_empty() && address.starts_with("0x")
+                })
+                .ok_or_else(|| ApiError::InvalidLibrary(library.clone()))?;
+
+            libraries
+                .entry(path.to_string())
+                .or_default()
+                .insert(name.to_string(), address.to_string());
+        }
+
+        Ok(libraries)
+    }
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
@@ -46,27 +144,237 @@ pub struct CompilationRequest {
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 #[serde(crate = "rocket::serde")]
 pub struct VerifyConfig {
+    /// Taken from the compilation when verifying by `compilation_id`.
+    #[serde(default)]
     pub zksolc_version: String,
     pub solc_version: Option<String>,
     pub network: String,
     pub contract_address: String,
-    pub inputs: Vec<String>,
+    /// Constructor arguments as JSON values, e.g. `["0x…", ["1", "2"], true]`. Integers
+    /// are best given as strings to keep their precision.
+    #[serde(default)]
+    pub inputs: Vec<Value>,
+    /// ABI encoded constructor arguments, taking precedence over `inputs`.
+    #[serde(default)]
+    pub constructor_args: Option<String>,
+    /// Compare the local compilation with the deployed bytecode before submitting,
+    /// defaults to the `verification.check_bytecode` setting.
+    #[serde(default)]
+    pub check_bytecode: Option<bool>,
+    /// Bytecode to compare against, fetched from the network's RPC when not given.
+    #[serde(default)]
+    pub deployed_bytecode: Option<String>,
+    /// The remaining compilation settings, which have to match the deployed contract's.
+    #[serde(default)]
+    pub optimizer: OptimizerSettings,
+    #[serde(default)]
+    pub user_libraries: Vec<String>,
+    /// Submits to this verifier of the network instead of running `hardhat verify`.
+    #[serde(default)]
+    pub verifier: Option<VerifierKind>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 #[serde(crate = "rocket::serde")]
 pub struct VerificationRequest {
     pub config: VerifyConfig,
+    /// Not needed when `compilation_id` is given.
+    #[serde(default)]
     pub contracts: Vec<CompiledFile>,
     // In format: path/Some.sol:ContractName
     pub target_contract: Option<String>,
+    /// ABI of the target contract, from its compiled artifact. Constructor arguments are
+    /// checked against it.
+    #[serde(default)]
+    pub abi: Option<Value>,
+    /// Id of a `/compile` result, whose sources and settings are verified instead of
+    /// `contracts`.
+    #[serde(default)]
+    pub compilation_id: Option<String>,
+    /// Folder holding the sources, as given to `/compile`.
+    #[serde(default)]
+    pub target_path: Option<String>,
+    /// Verifies `contract_address` as an EIP-1967 proxy: its implementation first, then
+    /// the proxy itself if `target_contract` is given.
+    #[serde(default)]
+    pub proxy: Option<ProxyVerifyConfig>,
+}
+
+/// The implementation behind a proxy to verify, found at the proxy's implementation slot.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct ProxyVerifyConfig {
+    // In format: path/Some.sol:ContractName
+    pub implementation_contract: String,
+    #[serde(default)]
+    pub implementation_inputs: Vec<Value>,
+    #[serde(default)]
+    pub implementation_constructor_args: Option<String>,
+    #[serde(default)]
+    pub implementation_abi: Option<Value>,
+}
+
+impl VerificationRequest {
+    /// The compilation of the sources to verify, with the same settings `/compile` takes.
+    pub fn compilation_request(&self) -> CompilationRequest {
+        CompilationRequest {
+            config: CompilationConfig {
+                version: self.config.zksolc_version.clone(),
+                user_libraries: self.config.user_libraries.clone(),
+                solc_version: self.config.solc_version.clone(),
+                optimizer: self.config.optimizer.clone(),
+            },
+            contracts: self.contracts.clone(),
+            target_path: self.target_path.clone(),
+        }
+    }
+}
+
+/// Settings shared by the contracts of a batch verification.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct BatchVerifyConfig {
+    /// Taken from the compilation when verifying by `compilation_id`.
+    #[serde(default)]
+    pub zksolc_version: String,
+    pub solc_version: Option<String>,
+    pub network: String,
+    #[serde(default)]
+    pub check_bytecode: Option<bool>,
+    #[serde(default)]
+    pub optimizer: OptimizerSettings,
+    #[serde(default)]
+    pub user_libraries: Vec<String>,
+    #[serde(default)]
+    pub verifier: Option<VerifierKind>,
+}
+
+/// One contract of a batch verification, see `VerifyConfig` for the fields.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct BatchVerificationContract {
+    pub contract_address: String,
+    // In format: path/Some.sol:ContractName
+    pub target_contract: String,
+    #[serde(default)]
+    pub inputs: Vec<Value>,
+    #[serde(default)]
+    pub constructor_args: Option<String>,
+    #[serde(default)]
+    pub abi: Option<Value>,
+    #[serde(default)]
+    pub deployed_bytecode: Option<String>,
+}
+
+/// Verifies several contracts deployed from the same sources, compiling them once.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct BatchVerificationRequest {
+    pub config: BatchVerifyConfig,
+    /// Not needed when `compilation_id` is given.
+    #[serde(default)]
+    pub contracts: Vec<CompiledFile>,
+    #[serde(default)]
+    pub compilation_id: Option<String>,
+    #[serde(default)]
+    pub target_path: Option<String>,
+    pub verifications: Vec<BatchVerificationContract>,
+}
+
+impl BatchVerificationRequest {
+    /// Request verifying `contract` alone, from the sources of the batch.
+    pub fn verification_request(
+        &self,
+        contract: &BatchVerificationContract,
+    ) -> VerificationRequest {
+        VerificationRequest {
+            config: VerifyConfig {
+                zksolc_version: self.config.zksolc_version.clone(),
+                solc_version: self.config.solc_version.clone(),
+                network: self.config.network.clone(),
+                contract_address: contract.contract_address.clone(),
+                inputs: contract.inputs.clone(),
+                constructor_args: contract.constructor_args.clone(),
+                check_bytecode: self.config.check_bytecode,
+                deployed_bytecode: contract.deployed_bytecode.clone(),
+                optimizer: self.config.optimizer.clone(),
+                user_libraries: self.config.user_libraries.clone(),
+                verifier: self.config.verifier,
+            },
+            contracts: self.contracts.clone(),
+            target_contract: Some(contract.target_contract.clone()),
+            abi: contract.abi.clone(),
+            compilation_id: self.compilation_id.clone(),
+            target_path: self.target_path.clone(),
+            proxy: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct BatchVerificationResult {
+    pub contract_address: String,
+    pub target_contract: String,
+    #[serde(flatten)]
+    pub response: VerifyResponse,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct BatchVerifyResponse {
+    /// `Error` if any contract failed, else `Pending` if any is still pending.
+    pub status: String,
+    pub message: String,
+    /// In the order of the request's `verifications`.
+    pub results: Vec<BatchVerificationResult>,
+    /// Set on failed verifications to correlate them with the server logs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+impl BatchVerifyResponse {
+    pub fn new(results: Vec<BatchVerificationResult>) -> Self {
+        let status_of = |status: &str| {
+            results
+                .iter()
+                .any(|result| result.response.status == status)
+        };
+        let status = if status_of("Error") {
+            "Error"
+        } else if status_of("Pending") {
+            "Pending"
+        } else {
+            "Success"
+        };
+        let verified = results
+            .iter()
+            .filter(|result| result.response.status == "Success")
+            .count();
+
+        Self {
+            status: status.to_string(),
+            message: format!("{} of {} contracts verified", verified, results.len()),
+            results,
+            request_id: None,
+        }
+    }
+
+    /// Attaches the id of the originating request to unsuccessful responses.
+    pub fn tag_failure(mut self, request_id: &str) -> Self {
+        if self.status != "Success" {
+            self.request_id = Some(request_id.to_string());
+        }
+        self
+    }
 }
 
 #[derive(Debug)]
 pub enum ApiCommand {
     CompilerVersion,
     Compile(CompilationRequest),
-    Verify(VerificationRequest),
+    Verify(Box<VerificationRequest>),
+    VerifyBatch(Box<BatchVerificationRequest>),
     #[allow(dead_code)]
     Shutdown,
 }
@@ -76,6 +384,7 @@ pub enum ApiCommandResult {
     CompilerVersion(String),
     Compile(CompileResponse),
     Verify(VerifyResponse),
+    VerifyBatch(BatchVerifyResponse),
     #[allow(dead_code)]
     Shutdown,
 }
@@ -1,14 +1,18 @@
 use tracing::instrument;
 
-#[instrument]
+use crate::client_ip::ClientIp;
+use crate::config::app_config;
+use crate::request_id::RequestId;
+
+#[instrument(skip(request_id, client_ip), fields(%request_id, client = %client_ip.tag()))]
 #[get("/service_version")]
-pub async fn service_version() -> String {
+pub async fn service_version(request_id: RequestId, client_ip: ClientIp) -> String {
     tracing::info!("/service_version");
-    std::env::var("SERVICE_VERSION").unwrap_or_else(|_| String::from("unknown"))
+    app_config().service_version.clone()
 }
 
-#[instrument]
+#[instrument(skip(request_id, client_ip), fields(%request_id, client = %client_ip.tag()))]
 #[post("/on-plugin-launched")]
-pub async fn on_plugin_launched() {
+pub async fn on_plugin_launched(request_id: RequestId, client_ip: ClientIp) {
     tracing::info!("/on-plugin-launched");
 }
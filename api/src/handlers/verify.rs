@@ -2,59 +2,107 @@ use rocket::serde::{json, json::Json};
 use rocket::{tokio, State};
 use std::path::Path;
 use std::process::Stdio;
+use std::time::Instant;
 use tracing::info;
-use tracing::instrument;
-
-use crate::errors::{ApiError, Result};
+use tracing::{info_span, instrument, Instrument};
+use uuid::Uuid;
+
+use crate::client_ip::ClientIp;
+use crate::compilation_store::compilation_store;
+use crate::compiler_registry::{compiler_registry, CompilerKind};
+use crate::config::app_config;
+use crate::errors::{ApiError, Result, VerificationError};
+use crate::handlers::compile::{do_compile, CompileMode};
 use crate::handlers::process::{do_process_command, fetch_process_result};
-use crate::handlers::types::{ApiCommand, ApiCommandResult, VerificationRequest, VerifyResponse};
-use crate::handlers::SPAWN_SEMAPHORE;
+use crate::handlers::types::{
+    ApiCommand, ApiCommandResult, CompiledFile, VerificationRequest, VerifyResponse,
+};
+use crate::handlers::verify_proxy::do_verify_proxy;
+use crate::handlers::{observe_action, SPAWN_SEMAPHORE};
 use crate::metrics::Metrics;
+use crate::networks::NetworkConfig;
 use crate::rate_limiter::RateLimited;
+use crate::request_id::RequestId;
 use crate::utils::cleaner::AutoCleanUp;
 use crate::utils::hardhat_config::HardhatConfigBuilder;
+use crate::utils::job_log::{wait_with_captured_output, JobLog};
 use crate::utils::lib::{
-    generate_folder_name, initialize_files, ALLOWED_NETWORKS, DEFAULT_SOLIDITY_VERSION, SOL_ROOT,
-    ZKSOLC_VERSIONS,
+    generate_folder_name, initialize_files, DEFAULT_SOLIDITY_VERSION, SOL_ROOT,
 };
+use crate::verification::backend::{verification_backend, VerifierKind};
+use crate::verification::bytecode::{artifact_bytecode, compare_bytecode};
+use crate::verification::constructor_args::ConstructorArgs;
+use crate::verification::outcome::VerificationOutcome;
+use crate::verification::rpc::RpcClient;
+use crate::verification::VerificationPayload;
 use crate::worker::WorkerEngine;
 
-pub(crate) const VERIFICATION_LABEL_VALUE: &str = "compilation";
+pub(crate) const VERIFICATION_LABEL_VALUE: &str = "verification";
 
-#[instrument(skip(verification_request_json, _rate_limited, engine))]
+/// Module exporting the constructor arguments, handed to `hardhat verify` so that they
+/// don't go through the command line.
+const CONSTRUCTOR_ARGS_FILE: &str = "constructor-args.js";
+
+#[instrument(
+    skip(verification_request_json, _rate_limited, request_id, client_ip, engine),
+    fields(%request_id, client = %client_ip.tag())
+)]
 #[post("/verify", format = "json", data = "<verification_request_json>")]
 pub async fn verify(
     verification_request_json: Json<VerificationRequest>,
     _rate_limited: RateLimited,
+    request_id: RequestId,
+    client_ip: ClientIp,
     engine: &State<WorkerEngine>,
 ) -> Json<VerifyResponse> {
     info!("/verify/{:?}", verification_request_json.config);
 
-    do_verify(verification_request_json.0, &engine.metrics)
-        .await
-        .unwrap_or_else(|e| {
-            Json(VerifyResponse {
-                message: e.to_string(),
-                status: "Error".to_string(),
-            })
-        })
+    let zksolc_version = verification_request_json.config.zksolc_version.clone();
+    let start_time = Instant::now();
+    let result = do_verify(verification_request_json.0, &engine.metrics, None).await;
+    observe_action(
+        &engine.metrics,
+        VERIFICATION_LABEL_VALUE,
+        &zksolc_version,
+        result.as_ref().map(|response| response.status.as_str()),
+        None,
+        start_time.elapsed(),
+    );
+
+    let response = result.unwrap_or_else(|e| Json(VerifyResponse::from_error(&e)));
+
+    Json(response.into_inner().tag_failure(&request_id.0))
 }
 
-#[instrument(skip(verification_request_json, _rate_limited, engine))]
+#[instrument(
+    skip(verification_request_json, _rate_limited, request_id, client_ip, engine),
+    fields(%request_id, client = %client_ip.tag())
+)]
 #[post("/verify-async", format = "json", data = "<verification_request_json>")]
 pub fn verify_async(
     verification_request_json: Json<VerificationRequest>,
     _rate_limited: RateLimited,
+    request_id: RequestId,
+    client_ip: ClientIp,
     engine: &State<WorkerEngine>,
 ) -> String {
     info!("/verify-async/{:?}", verification_request_json.config);
 
-    do_process_command(ApiCommand::Verify(verification_request_json.0), engine)
+    do_process_command(
+        ApiCommand::Verify(Box::new(verification_request_json.0)),
+        &request_id,
+        engine,
+    )
 }
 
-#[instrument(skip(engine))]
+#[instrument(skip(request_id, client_ip, engine), fields(%request_id, client = %client_ip.tag()))]
 #[get("/verify-result/<process_id>")]
-pub async fn get_verify_result(process_id: String, engine: &State<WorkerEngine>) -> String {
+pub async fn get_verify_result(
+    process_id: String,
+    request_id: RequestId,
+    client_ip: ClientIp,
+    engine: &State<WorkerEngine>,
+) -> String {
     info!("/verify-result/{:?}", process_id);
 
     fetch_process_result(process_id, engine, |result| match result {
@@ -65,62 +113,187 @@ pub async fn get_verify_result(process_id: String, engine: &State<WorkerEngine>)
     })
 }
 
-fn extract_verify_args(request: &VerificationRequest) -> Vec<String> {
-    let mut args: Vec<String> = vec!["hardhat".into(), "verify".into(), "--network".into()];
-    if request.config.network == "sepolia" {
-        args.push("zkSyncTestnet".into())
-    } else {
-        args.push("zkSyncMainnet".into())
+/// Replaces the sources and compiler settings of `verification_request` with the ones of
+/// the compilation it refers to, if any, so that exactly what was compiled gets verified.
+fn resolve_compilation(verification_request: &mut VerificationRequest) -> Result<()> {
+    let Some(ref compilation_id) = verification_request.compilation_id else {
+        return Ok(());
+    };
+
+    let compilation = Uuid::parse_str(compilation_id)
+        .ok()
+        .and_then(|id| compilation_store().get(&id))
+        .ok_or_else(|| ApiError::CompilationNotFound(compilation_id.clone()))?;
+
+    verification_request.contracts = compilation.contracts;
+    verification_request.config.zksolc_version = compilation.config.version;
+    verification_request.config.solc_version = compilation.config.solc_version;
+    verification_request.config.optimizer = compilation.config.optimizer;
+    verification_request.config.user_libraries = compilation.config.user_libraries;
+    verification_request.target_path = compilation.target_path;
+
+    Ok(())
+}
+
+/// Checks the zksolc version and network of `verification_request`, once its stored
+/// compilation is resolved. Returns the solc version to verify with and the network.
+pub(crate) fn prepare_verification(
+    verification_request: &mut VerificationRequest,
+) -> Result<(String, &'static NetworkConfig)> {
+    resolve_compilation(verification_request)?;
+    let zksolc_version = &verification_request.config.zksolc_version;
+
+    // check if the version is supported
+    if !compiler_registry().is_supported(CompilerKind::Zksolc, zksolc_version) {
+        return Err(ApiError::VersionNotSupported(zksolc_version.clone()));
     }
 
+    let solc_version = verification_request
+        .config
+        .solc_version
+        .clone()
+        .unwrap_or(DEFAULT_SOLIDITY_VERSION.to_string());
+    compiler_registry().check_solc_version(zksolc_version, &solc_version)?;
+
+    // check if the network is supported
+    let network = &verification_request.config.network;
+    let network_config = app_config()
+        .network(network)
+        .ok_or_else(|| ApiError::UnknownNetwork(network.clone()))?;
+
+    Ok((solc_version, network_config))
+}
+
+pub(crate) fn should_check_bytecode(verification_request: &VerificationRequest) -> bool {
+    verification_request
+        .config
+        .check_bytecode
+        .unwrap_or(app_config().verification.check_bytecode)
+}
+
+fn extract_verify_args(
+    request: &VerificationRequest,
+    constructor_args_file: Option<&str>,
+) -> Vec<String> {
+    let mut args: Vec<String> = vec![
+        "hardhat".into(),
+        "verify".into(),
+        "--network".into(),
+        request.config.network.clone(),
+    ];
+
     if let Some(ref target_contract) = request.target_contract {
         args.push("--contract".into());
         args.push(target_contract.clone());
     }
 
+    if let Some(constructor_args_file) = constructor_args_file {
+        args.push("--constructor-args".into());
+        args.push(constructor_args_file.into());
+    }
+
     args.push(request.config.contract_address.clone());
-    args.extend(request.config.inputs.clone());
 
     args
 }
 
+pub(crate) fn count_outcome(metrics: &Metrics, response: &VerifyResponse) {
+    let counter = if response.status == "Error" {
+        &metrics.action_failures_total
+    } else {
+        &metrics.action_successes_total
+    };
+    counter.with_label_values(&[VERIFICATION_LABEL_VALUE]).inc();
+}
+
+#[instrument(skip_all)]
 pub async fn do_verify(
-    verification_request: VerificationRequest,
+    mut verification_request: VerificationRequest,
     metrics: &Metrics,
+    job_log: Option<&JobLog>,
 ) -> Result<Json<VerifyResponse>> {
-    let zksolc_version = verification_request.config.zksolc_version.clone();
-
-    // check if the version is supported
-    if !ZKSOLC_VERSIONS.contains(&zksolc_version.as_str()) {
-        return Err(ApiError::VersionNotSupported(zksolc_version));
+    if let Some(proxy) = verification_request.proxy.take() {
+        return do_verify_proxy(verification_request, proxy, metrics, job_log).await;
     }
 
-    let solc_version = verification_request
-        .config
-        .solc_version
-        .clone()
-        .unwrap_or(DEFAULT_SOLIDITY_VERSION.to_string());
+    let (solc_version, network_config) = prepare_verification(&mut verification_request)?;
 
-    let network = verification_request.config.network.clone();
+    // check the constructor arguments before anything is submitted
+    let constructor_args = ConstructorArgs::from_request(&verification_request)?;
 
-    // check if the network is supported
-    if !ALLOWED_NETWORKS.contains(&network.as_str()) {
-        return Err(ApiError::UnknownNetwork(network));
+    if should_check_bytecode(&verification_request) {
+        let local_compilation =
+            compile_locally(&verification_request, &solc_version, metrics).await?;
+        if let Some(response) =
+            check_deployed_bytecode(&verification_request, network_config, &local_compilation)
+                .await?
+        {
+            count_outcome(metrics, &response);
+            return Ok(Json(response));
+        }
+    }
+
+    if uses_native_verifier(&verification_request) {
+        let response = do_native_verify(
+            &verification_request,
+            network_config,
+            &solc_version,
+            &constructor_args,
+        )
+        .await?;
+        count_outcome(metrics, &response);
+
+        return Ok(Json(response));
     }
 
+    let constructor_arg_values = constructor_args
+        .values
+        .ok_or(VerificationError::ConstructorAbiRequired)?;
+
     let namespace = generate_folder_name();
 
     // root directory for the contracts
     let workspace_path_str = format!("{}/{}", SOL_ROOT, namespace);
     let workspace_path = Path::new(&workspace_path_str);
 
-    // root directory for the artifacts
-    let artifacts_path_str = format!("{}/{}", workspace_path_str, "artifacts-zk");
-    let artifacts_path = Path::new(&artifacts_path_str);
+    // when the verification is done, clean up the directories
+    // it will be called when the AutoCleanUp struct is dropped
+    let auto_clean_up = AutoCleanUp {
+        dirs: vec![workspace_path.to_str().unwrap()],
+    };
+
+    setup_verify_workspace(&verification_request, workspace_path)
+        .instrument(info_span!("workspace_setup"))
+        .await?;
 
-    // root directory for user files (hardhat config, etc)
-    let user_files_path_str = workspace_path_str.clone();
-    let hardhat_config_path = Path::new(&user_files_path_str).join("hardhat.config.ts");
+    let response = run_hardhat_verify(
+        &verification_request,
+        &constructor_arg_values,
+        workspace_path,
+        CONSTRUCTOR_ARGS_FILE,
+        job_log,
+    )
+    .await?;
+
+    // calling here explicitly to avoid dropping the AutoCleanUp struct
+    auto_clean_up
+        .clean_up()
+        .instrument(info_span!("cleanup"))
+        .await;
+
+    count_outcome(metrics, &response);
+    Ok(Json(response))
+}
+
+/// Writes the sources and hardhat config of `verification_request` to `workspace_path`.
+/// Every contract verified from the same sources can share the workspace.
+pub(crate) async fn setup_verify_workspace(
+    verification_request: &VerificationRequest,
+    workspace_path: &Path,
+) -> Result<()> {
+    // root directory for the artifacts
+    let artifacts_path = workspace_path.join("artifacts-zk");
+    let hardhat_config_path = workspace_path.join("hardhat.config.ts");
 
     // instantly create the directories
     tokio::fs::create_dir_all(workspace_path)
@@ -130,72 +303,236 @@ pub async fn do_verify(
         .await
         .map_err(ApiError::FailedToWriteFile)?;
 
-    // when the compilation is done, clean up the directories
-    // it will be called when the AutoCleanUp struct is dropped
-    let auto_clean_up = AutoCleanUp {
-        dirs: vec![workspace_path.to_str().unwrap()],
-    };
-
     // write the hardhat config file
-    let hardhat_config_content = HardhatConfigBuilder::new()
-        .zksolc_version(&zksolc_version)
-        .solidity_version(&solc_version)
-        .build()
-        .to_string_config();
-
-    // create parent directories
-    tokio::fs::create_dir_all(hardhat_config_path.parent().unwrap())
-        .await
-        .map_err(ApiError::FailedToWriteFile)?;
+    let compilation_request = verification_request.compilation_request();
+    let hardhat_config_content = HardhatConfigBuilder::from_compilation(
+        &compilation_request.config,
+        compilation_request.target_path.as_deref(),
+    )?
+    .build()
+    .to_string_config();
 
     tokio::fs::write(hardhat_config_path, hardhat_config_content)
         .await
         .map_err(ApiError::FailedToWriteFile)?;
 
     // initialize the files
-    initialize_files(verification_request.contracts.clone(), workspace_path).await?;
-
-    // Limit number of spawned processes. RAII released
-    let _permit = SPAWN_SEMAPHORE.acquire().await.expect("Expired semaphore");
-
-    let args = extract_verify_args(&verification_request);
-    let command = tokio::process::Command::new("npx")
-        .args(args)
-        .current_dir(workspace_path)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn();
-
-    let process = command.map_err(ApiError::FailedToExecuteCommand)?;
-    let output = process
-        .wait_with_output()
+    initialize_files(verification_request.contracts.clone(), workspace_path).await
+}
+
+/// Runs `hardhat verify` in a workspace set up by `setup_verify_workspace`, handing it the
+/// constructor arguments through `constructor_args_file`.
+pub(crate) async fn run_hardhat_verify(
+    verification_request: &VerificationRequest,
+    constructor_arg_values: &[json::Value],
+    workspace_path: &Path,
+    constructor_args_file: &str,
+    job_log: Option<&JobLog>,
+) -> Result<VerifyResponse> {
+    let constructor_args_file = if constructor_arg_values.is_empty() {
+        None
+    } else {
+        let constructor_args_module = format!(
+            "module.exports = {};\n",
+            json::to_string(&constructor_arg_values).unwrap_or_default()
+        );
+        tokio::fs::write(
+            workspace_path.join(constructor_args_file),
+            constructor_args_module,
+        )
         .await
-        .map_err(ApiError::FailedToReadOutput)?;
-    let status = output.status;
-    let message = String::from_utf8_lossy(&output.stdout).to_string();
+        .map_err(ApiError::FailedToWriteFile)?;
 
-    // calling here explicitly to avoid dropping the AutoCleanUp struct
-    auto_clean_up.clean_up().await;
-
-    if !status.success() {
-        metrics
-            .action_failures_total
-            .with_label_values(&[VERIFICATION_LABEL_VALUE])
-            .inc();
-
-        return Ok(Json(VerifyResponse {
-            status: "Error".to_string(),
-            message: String::from_utf8_lossy(&output.stderr).to_string(),
-        }));
+        Some(constructor_args_file)
+    };
+
+    let output = async {
+        // Limit number of spawned processes. RAII released
+        let _permit = SPAWN_SEMAPHORE.acquire().await.expect("Expired semaphore");
+
+        let args = extract_verify_args(verification_request, constructor_args_file);
+        let command = tokio::process::Command::new("npx")
+            .args(args)
+            .current_dir(workspace_path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn();
+
+        let process = command.map_err(ApiError::FailedToExecuteCommand)?;
+        wait_with_captured_output(process, job_log).await
+    }
+    .instrument(info_span!("hardhat_spawn"))
+    .await?;
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr).to_string();
+        let outcome = VerificationOutcome::from_message(&format!("{}\n{}", message, stdout));
+        return Ok(VerifyResponse::new(outcome, message));
     }
 
-    metrics
-        .action_successes_total
-        .with_label_values(&[VERIFICATION_LABEL_VALUE])
-        .inc();
+    Ok(VerifyResponse::new(
+        VerificationOutcome::from_hardhat_output(&stdout),
+        stdout,
+    ))
+}
+
+/// Whether `verification_request` goes straight to a verifier API rather than through
+/// `hardhat verify`.
+pub(crate) fn uses_native_verifier(verification_request: &VerificationRequest) -> bool {
+    verification_request.config.verifier.is_some() || app_config().verification.native_client
+}
 
-    Ok(Json(VerifyResponse {
-        status: "Success".to_string(),
-        message,
-    }))
+/// Verifies through the requested verifier API of the network, its zkSync explorer by
+/// default.
+#[instrument(skip_all, fields(network = %network.name))]
+pub(crate) async fn do_native_verify(
+    verification_request: &VerificationRequest,
+    network: &NetworkConfig,
+    solc_version: &str,
+    constructor_args: &ConstructorArgs,
+) -> Result<VerifyResponse> {
+    let backend = verification_backend(
+        verification_request.config.verifier.unwrap_or_default(),
+        network,
+    )?;
+
+    let libraries = verification_request
+        .compilation_request()
+        .config
+        .libraries()?;
+    let payload = VerificationPayload::new(
+        verification_request,
+        solc_version,
+        &libraries,
+        constructor_args,
+    )?;
+    // Verifier failures are outcomes too, only invalid requests are errors
+    let mut response = match backend
+        .verify_contract(&payload, &app_config().verification)
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => return Ok(VerifyResponse::from_error(&e.into())),
+    };
+
+    if response.outcome == VerificationOutcome::Verified {
+        response.message = format!("Contract {} verified", payload.contract_name);
+    }
+    Ok(response)
+}
+
+#[instrument(skip(request_id, client_ip), fields(%request_id, client = %client_ip.tag()))]
+#[get("/verify-status/<verification_id>?<network>&<verifier>")]
+pub async fn get_verify_status(
+    verification_id: String,
+    network: Option<String>,
+    verifier: Option<VerifierKind>,
+    request_id: RequestId,
+    client_ip: ClientIp,
+) -> Json<VerifyResponse> {
+    info!("/verify-status/{}", verification_id);
+
+    let response = do_verify_status(verification_id, network, verifier)
+        .await
+        .unwrap_or_else(|e| VerifyResponse::from_error(&e));
+
+    Json(response.tag_failure(&request_id.0))
+}
+
+/// Current outcome of the verification `verification_id` at the `verifier` of `network`,
+/// the first registered network and its zkSync explorer by default.
+pub async fn do_verify_status(
+    verification_id: String,
+    network: Option<String>,
+    verifier: Option<VerifierKind>,
+) -> Result<VerifyResponse> {
+    let config = app_config();
+    let network = match network {
+        Some(name) => config
+            .network(&name)
+            .ok_or(ApiError::UnknownNetwork(name))?,
+        None => config
+            .networks
+            .first()
+            .ok_or_else(|| ApiError::UnknownNetwork(String::new()))?,
+    };
+
+    let backend = verification_backend(verifier.unwrap_or_default(), network)?;
+    Ok(backend.verification_status(&verification_id).await?)
+}
+
+/// Artifacts of the sources compiled the way they are verified, to compare with the
+/// deployed bytecode.
+pub(crate) struct LocalCompilation {
+    artifacts: Vec<CompiledFile>,
+    settings: json::Value,
+}
+
+/// Compiles the sources of `verification_request` with its settings.
+pub(crate) async fn compile_locally(
+    verification_request: &VerificationRequest,
+    solc_version: &str,
+    metrics: &Metrics,
+) -> Result<LocalCompilation> {
+    let mut compilation_request = verification_request.compilation_request();
+    compilation_request.config.solc_version = Some(solc_version.to_string());
+    let settings = json::json!({
+        "zksolc_version": compilation_request.config.version,
+        "solc_version": solc_version,
+        "optimizer": compilation_request.config.optimizer,
+        "libraries": compilation_request.config.user_libraries,
+        "target_path": compilation_request.target_path,
+    });
+    let compilation = do_compile(compilation_request, metrics, CompileMode::Internal, None)
+        .await?
+        .into_inner();
+    if compilation.status != "Success" {
+        return Err(VerificationError::CompilationFailed(compilation.message).into());
+    }
+
+    Ok(LocalCompilation {
+        artifacts: compilation.file_content,
+        settings,
+    })
+}
+
+/// Compares the target contract's bytecode in `local_compilation` with the deployed one,
+/// taken from the request or the network's RPC. Returns the response to give up with when
+/// they differ.
+#[instrument(skip_all, fields(network = %network.name))]
+pub(crate) async fn check_deployed_bytecode(
+    verification_request: &VerificationRequest,
+    network: &NetworkConfig,
+    local_compilation: &LocalCompilation,
+) -> Result<Option<VerifyResponse>> {
+    let target_contract = verification_request
+        .target_contract
+        .as_deref()
+        .ok_or(VerificationError::MissingTargetContract)?;
+
+    let deployed = match verification_request.config.deployed_bytecode {
+        Some(ref bytecode) => hex::decode(bytecode.trim_start_matches("0x"))
+            .map_err(|e| VerificationError::InvalidBytecode(e.to_string()))?,
+        None => {
+            RpcClient::new(&network.rpc_url)
+                .get_code(&verification_request.config.contract_address)
+                .await?
+        }
+    };
+
+    let local = artifact_bytecode(&local_compilation.artifacts, target_contract)?;
+    let comparison = compare_bytecode(&local, &deployed, local_compilation.settings.clone());
+    if comparison.matches {
+        return Ok(None);
+    }
+
+    let outcome = if comparison.deployed_length == 0 {
+        VerificationOutcome::ContractNotFound
+    } else {
+        VerificationOutcome::BytecodeMismatch
+    };
+    let mut response = VerifyResponse::new(outcome, comparison.differences.join("\n"));
+    response.bytecode_check = Some(comparison);
+    Ok(Some(response))
 }
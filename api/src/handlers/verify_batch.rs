@@ -0,0 +1,342 @@
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::{json, json::Json};
+use rocket::State;
+use std::path::Path;
+use tracing::info;
+use tracing::{info_span, instrument, Instrument};
+
+use crate::client_ip::ClientIp;
+use crate::config::app_config;
+use crate::errors::{ApiError, Result, VerificationError};
+use crate::handlers::process::{do_process_command, fetch_process_result};
+use crate::handlers::types::{
+    ApiCommand, ApiCommandResult, BatchVerificationContract, BatchVerificationRequest,
+    BatchVerificationResult, BatchVerifyResponse, VerificationRequest, VerifyResponse,
+};
+use crate::handlers::verify::{
+    check_deployed_bytecode, compile_locally, count_outcome, do_native_verify,
+    prepare_verification, run_hardhat_verify, setup_verify_workspace, should_check_bytecode,
+    uses_native_verifier, LocalCompilation,
+};
+use crate::metrics::Metrics;
+use crate::networks::NetworkConfig;
+use crate::rate_limiter::{RateLimited, RateLimiter};
+use crate::request_id::RequestId;
+use crate::utils::cleaner::AutoCleanUp;
+use crate::utils::job_log::{JobLog, LogStream};
+use crate::utils::lib::{generate_folder_name, SOL_ROOT};
+use crate::verification::constructor_args::ConstructorArgs;
+use crate::worker::WorkerEngine;
+
+#[instrument(
+    skip(
+        verification_request_json,
+        _rate_limited,
+        request_id,
+        client_ip,
+        rate_limiter,
+        engine
+    ),
+    fields(%request_id, client = %client_ip.tag())
+)]
+#[post(
+    "/verify-batch-async",
+    format = "json",
+    data = "<verification_request_json>"
+)]
+pub fn verify_batch_async(
+    verification_request_json: Json<BatchVerificationRequest>,
+    _rate_limited: RateLimited,
+    request_id: RequestId,
+    client_ip: ClientIp,
+    rate_limiter: &State<RateLimiter>,
+    engine: &State<WorkerEngine>,
+) -> std::result::Result<String, Custom<String>> {
+    info!(
+        "/verify-batch-async/{:?} ({} contracts)",
+        verification_request_json.config,
+        verification_request_json.verifications.len()
+    );
+
+    let batch_size = verification_request_json.verifications.len();
+    check_batch_size(batch_size, app_config().verification.max_batch_size)
+        .map_err(|e| Custom(Status::BadRequest, e.to_string()))?;
+    rate_limiter
+        .charge_batch(client_ip.0, Some("verify_batch_async"), batch_size)
+        .map_err(|_| {
+            Custom(
+                Status::TooManyRequests,
+                ApiError::TooManyRequests.to_string(),
+            )
+        })?;
+
+    Ok(do_process_command(
+        ApiCommand::VerifyBatch(Box::new(verification_request_json.0)),
+        &request_id,
+        engine,
+    ))
+}
+
+#[instrument(skip(request_id, client_ip, engine), fields(%request_id, client = %client_ip.tag()))]
+#[get("/verify-batch-result/<process_id>")]
+pub async fn get_verify_batch_result(
+    process_id: String,
+    request_id: RequestId,
+    client_ip: ClientIp,
+    engine: &State<WorkerEngine>,
+) -> String {
+    info!("/verify-batch-result/{:?}", process_id);
+
+    fetch_process_result(process_id, engine, |result| match result {
+        ApiCommandResult::VerifyBatch(verification_result) => {
+            json::to_string(&verification_result).unwrap_or_default()
+        }
+        _ => String::from("Result not available"),
+    })
+}
+
+/// Batches have to list at least one contract and at most `max_batch_size`, each of them
+/// possibly keeping a worker busy for the whole verification timeout.
+fn check_batch_size(batch_size: usize, max_batch_size: usize) -> Result<()> {
+    match batch_size {
+        0 => Err(ApiError::EmptyBatch),
+        size if size > max_batch_size => Err(ApiError::BatchTooLarge(size, max_batch_size)),
+        _ => Ok(()),
+    }
+}
+
+/// Request verifying `contract` from the already resolved sources and settings of `batch`.
+fn contract_request(
+    batch: &VerificationRequest,
+    contract: &BatchVerificationContract,
+) -> VerificationRequest {
+    let mut request = batch.clone();
+    request.config.contract_address = contract.contract_address.clone();
+    request.config.inputs = contract.inputs.clone();
+    request.config.constructor_args = contract.constructor_args.clone();
+    request.config.deployed_bytecode = contract.deployed_bytecode.clone();
+    request.target_contract = Some(contract.target_contract.clone());
+    request.abi = contract.abi.clone();
+
+    request
+}
+
+/// Verifies every contract of `batch_request`, one after the other. The sources are
+/// compiled once for the bytecode checks and `hardhat verify` runs in a single workspace,
+/// so only the first contract pays for the compilation. A failing contract doesn't stop
+/// the others, only invalid shared settings fail the whole batch.
+#[instrument(skip_all)]
+pub async fn do_verify_batch(
+    batch_request: BatchVerificationRequest,
+    metrics: &Metrics,
+    job_log: Option<&JobLog>,
+) -> Result<Json<BatchVerifyResponse>> {
+    check_batch_size(
+        batch_request.verifications.len(),
+        app_config().verification.max_batch_size,
+    )?;
+    let first = batch_request
+        .verifications
+        .first()
+        .ok_or(ApiError::EmptyBatch)?;
+
+    let mut batch = batch_request.verification_request(first);
+    let (solc_version, network) = prepare_verification(&mut batch)?;
+    // the sources are resolved, the contracts don't need to look them up again
+    batch.compilation_id = None;
+
+    let local_compilation = if should_check_bytecode(&batch) {
+        Some(compile_locally(&batch, &solc_version, metrics).await?)
+    } else {
+        None
+    };
+
+    let workspace_path_str = format!("{}/{}", SOL_ROOT, generate_folder_name());
+    let workspace_path = Path::new(&workspace_path_str);
+    let auto_clean_up = AutoCleanUp {
+        dirs: vec![workspace_path.to_str().unwrap()],
+    };
+
+    let native_client = uses_native_verifier(&batch);
+    if !native_client {
+        setup_verify_workspace(&batch, workspace_path)
+            .instrument(info_span!("workspace_setup"))
+            .await?;
+    }
+
+    let mut results = Vec::with_capacity(batch_request.verifications.len());
+    for (index, contract) in batch_request.verifications.iter().enumerate() {
+        log_progress(
+            job_log,
+            format!(
+                "Verifying {} at {}",
+                contract.target_contract, contract.contract_address
+            ),
+        );
+
+        let request = contract_request(&batch, contract);
+        let context = BatchContext {
+            network,
+            solc_version: &solc_version,
+            local_compilation: local_compilation.as_ref(),
+            workspace_path: (!native_client).then_some(workspace_path),
+        };
+        let response = verify_contract(&request, &context, index, job_log)
+            .await
+            .unwrap_or_else(|e| VerifyResponse::from_error(&e));
+        count_outcome(metrics, &response);
+
+        log_progress(
+            job_log,
+            format!(
+                "{}: {} {}",
+                contract.target_contract, response.status, response.message
+            ),
+        );
+        results.push(BatchVerificationResult {
+            contract_address: contract.contract_address.clone(),
+            target_contract: contract.target_contract.clone(),
+            response,
+        });
+    }
+
+    // calling here explicitly to avoid dropping the AutoCleanUp struct
+    auto_clean_up
+        .clean_up()
+        .instrument(info_span!("cleanup"))
+        .await;
+
+    Ok(Json(BatchVerifyResponse::new(results)))
+}
+
+/// What the contracts of a batch share.
+struct BatchContext<'a> {
+    network: &'a NetworkConfig,
+    solc_version: &'a str,
+    local_compilation: Option<&'a LocalCompilation>,
+    /// Set up for `hardhat verify`, none when verifying natively.
+    workspace_path: Option<&'a Path>,
+}
+
+#[instrument(skip_all, fields(contract = ?request.target_contract))]
+async fn verify_contract(
+    request: &VerificationRequest,
+    context: &BatchContext<'_>,
+    index: usize,
+    job_log: Option<&JobLog>,
+) -> Result<VerifyResponse> {
+    // check the constructor arguments before anything is submitted
+    let constructor_args = ConstructorArgs::from_request(request)?;
+
+    if let Some(local_compilation) = context.local_compilation {
+        if let Some(response) =
+            check_deployed_bytecode(request, context.network, local_compilation).await?
+        {
+            return Ok(response);
+        }
+    }
+
+    let Some(workspace_path) = context.workspace_path else {
+        return do_native_verify(
+            request,
+            context.network,
+            context.solc_version,
+            &constructor_args,
+        )
+        .await;
+    };
+
+    let constructor_arg_values = constructor_args
+        .values
+        .ok_or(VerificationError::ConstructorAbiRequired)?;
+    // one module per contract, they all live in the shared workspace
+    let constructor_args_file = format!("constructor-args-{}.js", index);
+    run_hardhat_verify(
+        request,
+        &constructor_arg_values,
+        workspace_path,
+        &constructor_args_file,
+        job_log,
+    )
+    .await
+}
+
+fn log_progress(job_log: Option<&JobLog>, line: String) {
+    if let Some(job_log) = job_log {
+        job_log.push(LogStream::Stdout, line);
+    }
+}
+
+#[test]
+fn test_batch_contract_requests() {
+    use crate::handlers::types::{BatchVerifyConfig, CompiledFile};
+    use crate::verification::outcome::VerificationOutcome;
+    use crate::verification::OptimizerSettings;
+
+    let contract = |address: &str, name: &str| BatchVerificationContract {
+        contract_address: address.to_string(),
+        target_contract: format!("contracts/Token.sol:{}", name),
+        inputs: vec![json::json!(address)],
+        constructor_args: None,
+        abi: None,
+        deployed_bytecode: None,
+    };
+    let batch_request = BatchVerificationRequest {
+        config: BatchVerifyConfig {
+            zksolc_version: "1.5.6".to_string(),
+            solc_version: None,
+            network: "sepolia".to_string(),
+            check_bytecode: None,
+            optimizer: OptimizerSettings::default(),
+            user_libraries: vec![],
+            verifier: None,
+        },
+        contracts: vec![CompiledFile {
+            file_name: "contracts/Token.sol".to_string(),
+            file_content: "contract Token {}".to_string(),
+            is_contract: true,
+        }],
+        compilation_id: None,
+        target_path: Some("contracts".to_string()),
+        verifications: vec![contract("0x01", "Token"), contract("0x02", "Proxy")],
+    };
+
+    let batch = batch_request.verification_request(&batch_request.verifications[0]);
+    let request = contract_request(&batch, &batch_request.verifications[1]);
+    assert_eq!(request.config.contract_address, "0x02");
+    assert_eq!(request.config.inputs, vec![json::json!("0x02")]);
+    assert_eq!(
+        request.target_contract.as_deref(),
+        Some("contracts/Token.sol:Proxy")
+    );
+    assert_eq!(request.target_path.as_deref(), Some("contracts"));
+    assert_eq!(request.contracts.len(), 1);
+
+    let result = |outcome: VerificationOutcome| BatchVerificationResult {
+        contract_address: "0x01".to_string(),
+        target_contract: "contracts/Token.sol:Token".to_string(),
+        response: VerifyResponse::new(outcome, String::new()),
+    };
+    let response = BatchVerifyResponse::new(vec![
+        result(VerificationOutcome::Verified),
+        result(VerificationOutcome::Pending {
+            verification_id: "1".to_string(),
+        }),
+    ]);
+    assert_eq!(response.status, "Pending");
+    assert_eq!(response.message, "1 of 2 contracts verified");
+
+    let response = BatchVerifyResponse::new(vec![
+        result(VerificationOutcome::AlreadyVerified),
+        result(VerificationOutcome::BytecodeMismatch),
+    ]);
+    assert_eq!(response.status, "Error");
+
+    assert!(matches!(check_batch_size(0, 20), Err(ApiError::EmptyBatch)));
+    assert!(check_batch_size(20, 20).is_ok());
+    assert!(matches!(
+        check_batch_size(21, 20),
+        Err(ApiError::BatchTooLarge(21, 20))
+    ));
+}
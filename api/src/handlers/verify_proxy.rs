@@ -0,0 +1,104 @@
+use rocket::serde::json::Json;
+use tracing::instrument;
+
+use crate::config::app_config;
+use crate::errors::{ApiError, Result};
+use crate::handlers::types::{
+    BatchVerificationContract, BatchVerificationRequest, BatchVerifyConfig, ProxyVerifyConfig,
+    VerificationRequest, VerifyResponse,
+};
+use crate::handlers::verify_batch::do_verify_batch;
+use crate::metrics::Metrics;
+use crate::utils::job_log::JobLog;
+use crate::verification::proxy::{ProxyLinkage, ProxyVerification};
+use crate::verification::rpc::RpcClient;
+
+/// Verifies the implementation behind the proxy at `contract_address`, then the proxy itself
+/// when `target_contract` names it. Both share one compilation, as a batch of two.
+///
+/// The response is the first unsuccessful of the two verifications, or the proxy's, and
+/// links the proxy to its implementation.
+#[instrument(skip_all, fields(proxy = %verification_request.config.contract_address))]
+pub async fn do_verify_proxy(
+    verification_request: VerificationRequest,
+    proxy: ProxyVerifyConfig,
+    metrics: &Metrics,
+    job_log: Option<&JobLog>,
+) -> Result<Json<VerifyResponse>> {
+    let network = &verification_request.config.network;
+    let network_config = app_config()
+        .network(network)
+        .ok_or_else(|| ApiError::UnknownNetwork(network.clone()))?;
+
+    let linkage = ProxyLinkage::read(
+        &RpcClient::new(&network_config.rpc_url),
+        &verification_request.config.contract_address,
+    )
+    .await?;
+
+    let batch_request = proxy_batch(verification_request, proxy, &linkage);
+    let mut results = do_verify_batch(batch_request, metrics, job_log)
+        .await?
+        .into_inner()
+        .results
+        .into_iter()
+        .map(|result| result.response);
+    let implementation = results
+        .next()
+        .ok_or_else(|| ApiError::MissingBatchResult(linkage.implementation_address.clone()))?;
+
+    let mut response = match results.next() {
+        Some(proxy) if implementation.status == "Success" => proxy,
+        _ => implementation.clone(),
+    };
+    response.proxy = Some(Box::new(ProxyVerification {
+        linkage,
+        implementation: Box::new(implementation),
+    }));
+
+    Ok(Json(response))
+}
+
+/// The implementation at `linkage`, and the proxy if it is named, as a batch.
+fn proxy_batch(
+    verification_request: VerificationRequest,
+    proxy: ProxyVerifyConfig,
+    linkage: &ProxyLinkage,
+) -> BatchVerificationRequest {
+    let config = verification_request.config;
+
+    let mut verifications = vec![BatchVerificationContract {
+        contract_address: linkage.implementation_address.clone(),
+        target_contract: proxy.implementation_contract,
+        inputs: proxy.implementation_inputs,
+        constructor_args: proxy.implementation_constructor_args,
+        abi: proxy.implementation_abi,
+        deployed_bytecode: None,
+    }];
+    if let Some(target_contract) = verification_request.target_contract {
+        verifications.push(BatchVerificationContract {
+            contract_address: config.contract_address,
+            target_contract,
+            inputs: config.inputs,
+            constructor_args: config.constructor_args,
+            abi: verification_request.abi,
+            deployed_bytecode: config.deployed_bytecode,
+        });
+    }
+
+    BatchVerificationRequest {
+        config: BatchVerifyConfig {
+            zksolc_version: config.zksolc_version,
+            solc_version: config.solc_version,
+            network: config.network,
+            check_bytecode: config.check_bytecode,
+            optimizer: config.optimizer,
+            user_libraries: config.user_libraries,
+            verifier: config.verifier,
+        },
+        contracts: verification_request.contracts,
+        compilation_id: verification_request.compilation_id,
+        target_path: verification_request.target_path,
+        verifications,
+    }
+}
@@ -1,34 +1,48 @@
 #[macro_use]
 extern crate rocket;
 
+pub mod client_ip;
+pub mod compilation_store;
+pub mod compiler_cache;
+pub mod compiler_registry;
+pub mod config;
 pub mod cors;
 pub mod errors;
 pub mod handlers;
 mod metrics;
+pub mod networks;
 pub mod rate_limiter;
+pub mod request_id;
 pub mod tracing_log;
 pub mod utils;
+pub mod verification;
 pub mod worker;
 
 use clokwerk::{Scheduler, TimeUnits};
 use handlers::compile::{compile, compile_async, get_compile_result};
-use handlers::compiler_version::{allowed_versions, compiler_version};
-use handlers::process::get_process_status;
+use handlers::compiler_version::{allowed_versions, compiler_version, versions};
+use handlers::process::{get_process_logs, get_process_status};
 use handlers::utils::service_version;
-use handlers::verify::{get_verify_result, verify, verify_async};
+use handlers::verify::{get_verify_result, get_verify_status, verify, verify_async};
+use handlers::verify_batch::{get_verify_batch_result, verify_batch_async};
 use handlers::{health, who_is_this};
 use prometheus::Registry;
 use rocket::tokio::time::sleep;
 use rocket::{tokio, Build, Config, Rocket};
-use std::env;
 use std::net::Ipv4Addr;
+use std::sync::Arc;
 use tracing::info;
 
+use crate::compiler_cache::CompilerCache;
+use crate::compiler_registry::{admin_compilers, admin_rescan_compilers, compiler_registry};
+use crate::config::{admin_config, init_app_config, AppConfig};
 use crate::cors::CORS;
 use crate::errors::CoreError;
 use crate::handlers::utils::on_plugin_launched;
-use crate::metrics::{create_metrics, Metrics};
-use crate::rate_limiter::RateLimiter;
+use crate::metrics::{create_metrics, DistinctUsers, Metrics};
+use crate::networks::get_networks;
+use crate::rate_limiter::{RateLimitHeaders, RateLimiter};
+use crate::request_id::RequestIdFairing;
 use crate::tracing_log::init_logger;
 use crate::utils::lib::{ARTIFACTS_ROOT, SOL_ROOT};
 use crate::worker::WorkerEngine;
@@ -41,19 +55,9 @@ async fn clear_artifacts() {
     info!("artifacts cleared!");
 }
 
-fn create_app(metrics: Metrics) -> Rocket<Build> {
-    const DEFAULT_NUM_OF_WORKERS: u32 = 2u32;
-    const DEFAULT_QUEUE_SIZE: usize = 1_000;
-
-    let number_of_workers = match env::var("WORKER_THREADS") {
-        Ok(v) => v.parse::<u32>().unwrap_or(DEFAULT_NUM_OF_WORKERS),
-        Err(_) => DEFAULT_NUM_OF_WORKERS,
-    };
-
-    let queue_size = match env::var("QUEUE_SIZE") {
-        Ok(v) => v.parse::<usize>().unwrap_or(DEFAULT_QUEUE_SIZE),
-        Err(_) => DEFAULT_QUEUE_SIZE,
-    };
+fn create_app(config: &AppConfig, metrics: Metrics) -> Rocket<Build> {
+    let number_of_workers = config.worker.threads;
+    let queue_size = config.worker.queue_size;
 
     // Launch the worker processes
     let mut engine = WorkerEngine::new(number_of_workers, queue_size, metrics.clone());
@@ -83,9 +87,12 @@ fn create_app(metrics: Metrics) -> Rocket<Build> {
 
     rocket::build()
         .manage(engine)
-        .manage(RateLimiter::new())
+        .manage(RateLimiter::new(config.rate_limiter.clone()))
+        .manage(config.client_ip.clone())
+        .attach(RequestIdFairing)
         .attach(metrics)
-        .attach(CORS)
+        .attach(CORS::new(config.cors.clone()))
+        .attach(RateLimitHeaders)
         .mount(
             "/",
             routes![
@@ -95,9 +102,15 @@ fn create_app(metrics: Metrics) -> Rocket<Build> {
                 verify,
                 verify_async,
                 get_verify_result,
+                get_verify_status,
+                verify_batch_async,
+                get_verify_batch_result,
                 compiler_version,
                 get_process_status,
+                get_process_logs,
                 allowed_versions,
+                versions,
+                get_networks,
                 health,
                 who_is_this,
                 service_version,
@@ -106,13 +119,11 @@ fn create_app(metrics: Metrics) -> Rocket<Build> {
         )
 }
 
-fn create_metrics_server(registry: Registry) -> Rocket<Build> {
-    const DEFAULT_PORT: u16 = 8001;
-    let port = match env::var("METRICS_PORT") {
-        Ok(val) => val.parse::<u16>().unwrap_or(DEFAULT_PORT),
-        Err(_) => DEFAULT_PORT,
-    };
-
+fn create_metrics_server(
+    port: u16,
+    registry: Registry,
+    distinct_users: Arc<DistinctUsers>,
+) -> Rocket<Build> {
     let config = Config {
         port,
         address: Ipv4Addr::UNSPECIFIED.into(),
@@ -121,18 +132,40 @@ fn create_metrics_server(registry: Registry) -> Rocket<Build> {
 
     rocket::custom(config)
         .manage(registry)
-        .mount("/", routes![metrics::metrics])
+        .manage(distinct_users)
+        .mount(
+            "/",
+            routes![
+                metrics::metrics,
+                admin_config,
+                admin_compilers,
+                admin_rescan_compilers
+            ],
+        )
 }
 
 #[rocket::main]
 async fn main() -> Result<(), CoreError> {
-    init_logger()?;
+    // The logger depends on the configuration, so its errors can only go to stderr
+    let config = AppConfig::load().inspect_err(|e| eprintln!("{}", e))?;
+    init_app_config(config.clone())?;
+    let _tracing_guard = init_logger(&config.logging)?;
+
+    // Fill the compilers directory from the mirror, then scan it before any request needs it
+    if let Some(compiler_cache) = CompilerCache::from_config(&config)? {
+        compiler_cache.prewarm(&config.zksolc_versions);
+    }
+    compiler_registry().scan().await;
 
     let registry = Registry::new();
     let metrics = create_metrics(registry.clone())?;
 
-    let app = create_app(metrics);
-    let metrics_server = create_metrics_server(registry);
+    let metrics_server = create_metrics_server(
+        config.metrics_port,
+        registry,
+        metrics.distinct_users.clone(),
+    );
+    let app = create_app(&config, metrics);
 
     let (app_result, metrics_result) = rocket::tokio::join!(app.launch(), metrics_server.launch());
     app_result?;
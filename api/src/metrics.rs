@@ -1,24 +1,46 @@
-use prometheus::core::{AtomicF64, AtomicU64, GenericCounter, GenericCounterVec, GenericGaugeVec};
-use prometheus::{Encoder, GaugeVec, IntCounter, IntCounterVec, Opts, Registry, TextEncoder};
+use chrono::{DateTime, Datelike, Utc};
+use prometheus::core::{AtomicU64, GenericCounter, GenericCounterVec};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
 use rocket::fairing::{Fairing, Info, Kind};
 use rocket::http::Method;
 use rocket::{Data, Request, State};
-use tracing::debug;
+use std::collections::hash_map::RandomState;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tracing::instrument;
 
+use crate::client_ip::ClientIp;
 use crate::errors::CoreError;
+use crate::utils::hyperloglog::HyperLogLog;
 
 const NAMESPACE: &str = "zksync_api";
 
+/// Compilations and verifications take from under a second up to several minutes.
+const DURATION_BUCKETS: [f64; 12] = [
+    0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0, 300.0,
+];
+
 #[derive(Clone, Debug)]
 pub struct Metrics {
-    pub num_distinct_users: GenericCounterVec<AtomicU64>,
+    pub distinct_users: Arc<DistinctUsers>,
     pub num_plugin_launches: GenericCounter<AtomicU64>,
     pub num_of_compilations: GenericCounter<AtomicU64>,
     pub requests_total: GenericCounter<AtomicU64>,
     pub action_failures_total: GenericCounterVec<AtomicU64>,
     pub action_successes_total: GenericCounterVec<AtomicU64>,
-    pub action_duration_seconds: GenericGaugeVec<AtomicF64>,
+    pub action_queue_wait_seconds: HistogramVec,
+    pub action_execution_seconds: HistogramVec,
+    pub action_end_to_end_seconds: HistogramVec,
+    pub queue_depth: IntGauge,
+    pub process_states: IntGaugeVec,
+    pub workers: IntGaugeVec,
+    pub spawn_permits_available: IntGauge,
+    pub pending_purge: IntGauge,
+    pub workspace_disk_usage_bytes: IntGauge,
 }
 
 #[rocket::async_trait]
@@ -33,13 +55,8 @@ impl Fairing for Metrics {
     #[instrument(skip(self, req, _data))]
     async fn on_request(&self, req: &mut Request<'_>, _data: &mut Data<'_>) {
         self.requests_total.inc();
-        if let Some(val) = req.client_ip() {
-            let ip = val.to_string();
-            let ip = ip.as_str();
-            debug!("Plugin launched by: {}", ip);
-            debug!("Headers: {:?}", req.headers());
-
-            self.num_distinct_users.with_label_values(&[ip]).inc();
+        if let Some(ClientIp(val)) = ClientIp::resolve(req) {
+            self.distinct_users.observe(&val, Utc::now());
         }
 
         match req.method() {
@@ -50,6 +67,30 @@ impl Fairing for Metrics {
 }
 
 impl Metrics {
+    /// Records the latency of an action. `queue_wait` is only set for actions that went
+    /// through the worker queue, it is part of their end-to-end latency.
+    pub fn observe_action(
+        &self,
+        labels: &[&str; 3],
+        queue_wait: Option<Duration>,
+        execution: Duration,
+    ) {
+        let mut end_to_end = execution;
+        if let Some(queue_wait) = queue_wait {
+            self.action_queue_wait_seconds
+                .with_label_values(labels)
+                .observe(queue_wait.as_secs_f64());
+            end_to_end += queue_wait;
+        }
+
+        self.action_execution_seconds
+            .with_label_values(labels)
+            .observe(execution.as_secs_f64());
+        self.action_end_to_end_seconds
+            .with_label_values(labels)
+            .observe(end_to_end.as_secs_f64());
+    }
+
     fn update_metrics(&self, req: &mut Request<'_>) {
         match req.uri().path().as_str() {
             "/compile" | "/compile-async" => self.num_of_compilations.inc(),
@@ -59,12 +100,114 @@ impl Metrics {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+enum UsersWindow {
+    Daily,
+    Weekly,
+}
+
+impl UsersWindow {
+    fn label(&self) -> &'static str {
+        match self {
+            UsersWindow::Daily => "daily",
+            UsersWindow::Weekly => "weekly",
+        }
+    }
+
+    fn period_id(&self, now: DateTime<Utc>) -> i64 {
+        match self {
+            UsersWindow::Daily => i64::from(now.num_days_from_ce()),
+            UsersWindow::Weekly => {
+                let week = now.iso_week();
+                i64::from(week.year()) * 100 + i64::from(week.week())
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct UsersSketch {
+    window: UsersWindow,
+    period_id: i64,
+    sketch: HyperLogLog<RandomState>,
+}
+
+impl UsersSketch {
+    /// Starts a new sketch, with a new key, once `now` is past the current window.
+    fn roll_over(&mut self, now: DateTime<Utc>) {
+        let period_id = self.window.period_id(now);
+        if self.period_id != period_id {
+            self.period_id = period_id;
+            self.sketch = HyperLogLog::with_hasher(RandomState::new());
+        }
+    }
+}
+
+/// Estimates the number of distinct clients per day and per week without keeping their
+/// addresses. Each window hashes with a fresh random key, so sketches can't be joined
+/// across windows or matched against known addresses.
+#[derive(Debug)]
+pub struct DistinctUsers {
+    sketches: Mutex<Vec<UsersSketch>>,
+    gauge: IntGaugeVec,
+}
+
+impl DistinctUsers {
+    fn new(gauge: IntGaugeVec) -> Self {
+        let sketches = [UsersWindow::Daily, UsersWindow::Weekly]
+            .into_iter()
+            .map(|window| UsersSketch {
+                window,
+                period_id: 0,
+                sketch: HyperLogLog::with_hasher(RandomState::new()),
+            })
+            .collect();
+
+        Self {
+            sketches: Mutex::new(sketches),
+            gauge,
+        }
+    }
+
+    pub fn observe(&self, ip: &IpAddr, now: DateTime<Utc>) {
+        let Ok(mut sketches) = self.sketches.lock() else {
+            return;
+        };
+
+        for users in sketches.iter_mut() {
+            users.roll_over(now);
+            users.sketch.insert(ip);
+        }
+    }
+
+    /// Sets the gauges from the sketches. Estimating walks every register, so it is done
+    /// when the metrics are scraped rather than on each request.
+    pub fn refresh(&self, now: DateTime<Utc>) {
+        let Ok(mut sketches) = self.sketches.lock() else {
+            return;
+        };
+
+        for users in sketches.iter_mut() {
+            users.roll_over(now);
+            self.gauge
+                .with_label_values(&[users.window.label()])
+                .set(users.sketch.estimate().round() as i64);
+        }
+    }
+}
+
 pub(crate) fn create_metrics(registry: Registry) -> Result<Metrics, CoreError> {
     const ACTION_LABEL_NAME: &str = "action";
+    const DURATION_LABEL_NAMES: [&str; 3] = [ACTION_LABEL_NAME, "zksolc_version", "outcome"];
 
-    let opts = Opts::new("num_distinct_users", "Number of distinct users").namespace(NAMESPACE);
-    let num_distinct_users = IntCounterVec::new(opts, &["ip"])?;
-    registry.register(Box::new(num_distinct_users.clone()))?;
+    let opts = Opts::new(
+        "distinct_users",
+        "Estimated number of distinct users in the current window",
+    )
+    .namespace(NAMESPACE);
+    let distinct_users_gauge = IntGaugeVec::new(opts, &["window"])?;
+    registry.register(Box::new(distinct_users_gauge.clone()))?;
+    let distinct_users = Arc::new(DistinctUsers::new(distinct_users_gauge));
 
     let opts = Opts::new("num_plugin_launches", "Number of plugin launches").namespace(NAMESPACE);
     let num_plugin_launches = IntCounter::with_opts(opts)?;
@@ -88,25 +231,101 @@ pub(crate) fn create_metrics(registry: Registry) -> Result<Metrics, CoreError> {
     let action_successes_total = IntCounterVec::new(opts, &[ACTION_LABEL_NAME])?;
     registry.register(Box::new(action_successes_total.clone()))?;
 
+    let opts = HistogramOpts::new(
+        "action_queue_wait_seconds",
+        "Time an action spent in the worker queue in seconds",
+    )
+    .namespace(NAMESPACE)
+    .buckets(DURATION_BUCKETS.to_vec());
+    let action_queue_wait_seconds = HistogramVec::new(opts, &DURATION_LABEL_NAMES)?;
+    registry.register(Box::new(action_queue_wait_seconds.clone()))?;
+
+    let opts = HistogramOpts::new(
+        "action_execution_seconds",
+        "Time spent executing an action in seconds",
+    )
+    .namespace(NAMESPACE)
+    .buckets(DURATION_BUCKETS.to_vec());
+    let action_execution_seconds = HistogramVec::new(opts, &DURATION_LABEL_NAMES)?;
+    registry.register(Box::new(action_execution_seconds.clone()))?;
+
+    let opts = HistogramOpts::new(
+        "action_end_to_end_seconds",
+        "Time from receiving an action to its result in seconds",
+    )
+    .namespace(NAMESPACE)
+    .buckets(DURATION_BUCKETS.to_vec());
+    let action_end_to_end_seconds = HistogramVec::new(opts, &DURATION_LABEL_NAMES)?;
+    registry.register(Box::new(action_end_to_end_seconds.clone()))?;
+
+    let opts = Opts::new(
+        "queue_depth",
+        "Number of commands waiting in the worker queue",
+    )
+    .namespace(NAMESPACE);
+    let queue_depth = IntGauge::with_opts(opts)?;
+    registry.register(Box::new(queue_depth.clone()))?;
+
     let opts =
-        Opts::new("action_duration_seconds", "Duration of action in seconds").namespace(NAMESPACE);
-    let action_duration_seconds = GaugeVec::new(opts, &[ACTION_LABEL_NAME])?;
-    registry.register(Box::new(action_duration_seconds.clone()))?;
+        Opts::new("process_states", "Number of tracked processes per state").namespace(NAMESPACE);
+    let process_states = IntGaugeVec::new(opts, &["state"])?;
+    registry.register(Box::new(process_states.clone()))?;
+
+    let opts = Opts::new("workers", "Number of busy and idle workers").namespace(NAMESPACE);
+    let workers = IntGaugeVec::new(opts, &["state"])?;
+    registry.register(Box::new(workers.clone()))?;
+
+    let opts = Opts::new(
+        "spawn_permits_available",
+        "Number of compiler processes that can still be spawned",
+    )
+    .namespace(NAMESPACE);
+    let spawn_permits_available = IntGauge::with_opts(opts)?;
+    registry.register(Box::new(spawn_permits_available.clone()))?;
+
+    let opts = Opts::new(
+        "pending_purge",
+        "Number of finished processes waiting to be purged",
+    )
+    .namespace(NAMESPACE);
+    let pending_purge = IntGauge::with_opts(opts)?;
+    registry.register(Box::new(pending_purge.clone()))?;
+
+    let opts = Opts::new(
+        "workspace_disk_usage_bytes",
+        "Disk space used by the compilation workspaces in bytes",
+    )
+    .namespace(NAMESPACE);
+    let workspace_disk_usage_bytes = IntGauge::with_opts(opts)?;
+    registry.register(Box::new(workspace_disk_usage_bytes.clone()))?;
 
     Ok(Metrics {
-        num_distinct_users,
+        distinct_users,
         num_plugin_launches,
         num_of_compilations,
         requests_total,
         action_failures_total,
         action_successes_total,
-        action_duration_seconds,
+        action_queue_wait_seconds,
+        action_execution_seconds,
+        action_end_to_end_seconds,
+        queue_depth,
+        process_states,
+        workers,
+        spawn_permits_available,
+        pending_purge,
+        workspace_disk_usage_bytes,
     })
 }
 
-#[instrument(skip(registry))]
+#[instrument(skip(registry, distinct_users))]
 #[get("/metrics")]
-pub(crate) async fn metrics(registry: &State<Registry>) -> String {
+pub(crate) async fn metrics(
+    registry: &State<Registry>,
+    distinct_users: &State<Arc<DistinctUsers>>,
+) -> String {
+    distinct_users.refresh(Utc::now());
+
     let metric_families = registry.gather();
     let mut buffer = Vec::new();
     let encoder = TextEncoder::new();
@@ -0,0 +1,120 @@
+use rocket::figment::providers::{Format, Toml};
+use rocket::figment::Figment;
+use rocket::serde::json::serde_json;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tracing::{info, instrument};
+
+use crate::client_ip::ClientIp;
+use crate::config::app_config;
+use crate::request_id::RequestId;
+
+/// Network hardhat always defines itself, so a registered one can't take its name.
+const HARDHAT_NETWORK: &str = "hardhat";
+
+const BUNDLED_CONFIG: &str = include_str!("../Rocket.toml");
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct NetworkConfig {
+    /// Name the plugin sends in verification requests, also used as the hardhat network.
+    pub name: String,
+    pub chain_id: u64,
+    pub rpc_url: String,
+    /// L1 network name or RPC URL, hardhat's `ethNetwork`.
+    pub l1_network: String,
+    /// Contract verification endpoint of the network's block explorer.
+    pub verifier_url: Option<String>,
+    /// Etherscan-compatible API of the network, e.g. Era scan's.
+    #[serde(default)]
+    pub etherscan_url: Option<String>,
+    /// Never served, the network list is public.
+    #[serde(default, skip_serializing)]
+    pub etherscan_api_key: Option<String>,
+    /// Sourcify-style server taking standard JSON input.
+    #[serde(default)]
+    pub sourcify_url: Option<String>,
+}
+
+impl NetworkConfig {
+    /// The network entry of the hardhat config.
+    pub fn to_hardhat_network(&self) -> serde_json::Value {
+        let mut network = serde_json::json!({
+            "url": self.rpc_url,
+            "ethNetwork": self.l1_network,
+            "zksync": true,
+        });
+        if let Some(ref verifier_url) = self.verifier_url {
+            network["verifyURL"] = verifier_url.as_str().into();
+        }
+
+        network
+    }
+}
+
+/// Networks of `profile` in the bundled `Rocket.toml`.
+fn bundled_networks(profile: &str) -> Vec<NetworkConfig> {
+    Figment::from(Toml::string(BUNDLED_CONFIG).nested())
+        .select(profile)
+        .extract_inner("networks")
+        .unwrap_or_default()
+}
+
+/// The networks of the bundled `Rocket.toml`, used when the configuration lists none.
+pub fn default_networks() -> Vec<NetworkConfig> {
+    bundled_networks("default")
+}
+
+pub fn validate_networks(networks: &[NetworkConfig]) -> Result<(), String> {
+    if networks.is_empty() {
+        return Err("networks must list at least one network".to_string());
+    }
+
+    let mut names = HashSet::new();
+    let mut chain_ids = HashSet::new();
+    for network in networks {
+        if network.name.is_empty() || network.name == HARDHAT_NETWORK {
+            return Err(format!("networks: invalid name {:?}", network.name));
+        }
+        if !names.insert(network.name.as_str()) {
+            return Err(format!("networks: {} is listed twice", network.name));
+        }
+        if !chain_ids.insert(network.chain_id) {
+            return Err(format!(
+                "networks: chain id {} is used by more than one network",
+                network.chain_id
+            ));
+        }
+        if network.rpc_url.is_empty() {
+            return Err(format!("networks: {} has no rpc_url", network.name));
+        }
+    }
+
+    Ok(())
+}
+
+#[instrument(skip(request_id, client_ip), fields(%request_id, client = %client_ip.tag()))]
+#[get("/networks")]
+pub async fn get_networks(request_id: RequestId, client_ip: ClientIp) -> String {
+    info!("/networks");
+    serde_json::to_string(&app_config().networks).unwrap_or_default()
+}
+
+#[test]
+fn test_validate_networks() {
+    let mut networks = default_networks();
+    assert_eq!(validate_networks(&networks), Ok(()));
+    assert!(validate_networks(&[]).is_err());
+
+    networks[1].chain_id = networks[0].chain_id;
+    assert!(validate_networks(&networks).is_err());
+
+    networks[1].name = HARDHAT_NETWORK.to_string();
+    assert!(validate_networks(&networks).is_err());
+
+    let networks = bundled_networks("debug");
+    assert_eq!(validate_networks(&networks), Ok(()));
+    assert!(networks
+        .iter()
+        .any(|network| network.rpc_url == "http://127.0.0.1:8011"));
+}
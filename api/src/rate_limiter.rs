@@ -1,107 +1,352 @@
-use crate::errors::{ApiError, Result};
-use crate::utils::lib::timestamp;
-use crate::worker::Timestamp;
-use crossbeam_queue::ArrayQueue;
+use crate::client_ip::ClientIp;
+use crate::errors::ApiError;
 use crossbeam_skiplist::SkipMap;
-use rocket::http::Status;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{Header, Status};
 use rocket::request::{FromRequest, Outcome};
-use rocket::Request;
+use rocket::{Request, Response};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::net::IpAddr;
-use std::sync::{Arc, Mutex};
+use std::ops::Bound;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+pub const DEFAULT_POLICY_NAME: &str = "default";
+
+/// Bucket key: the client address and the name of the policy it is charged against.
+pub type BucketKey = (IpAddr, String);
+
+/// Theoretical arrival time (TAT) of the next request, in nanoseconds since the limiter epoch.
+pub type RateLimiterMap = SkipMap<BucketKey, AtomicU64>;
+
+/// Outcome of charging a request against a bucket, reported back in the response headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitDecision {
+    /// Burst size of the policy the request was charged against.
+    pub limit: u32,
+    /// Requests that can still be issued back to back.
+    pub remaining: u32,
+    /// Seconds until the bucket is full again.
+    pub reset: u64,
+    /// Seconds until the next request is accepted, only set for rejected requests.
+    pub retry_after: Option<u64>,
+}
 
-pub type Method = String;
+impl RateLimitDecision {
+    fn headers(&self) -> Vec<Header<'static>> {
+        let mut headers = vec![
+            Header::new("RateLimit-Limit", self.limit.to_string()),
+            Header::new("RateLimit-Remaining", self.remaining.to_string()),
+            Header::new("RateLimit-Reset", self.reset.to_string()),
+        ];
+        if let Some(retry_after) = self.retry_after {
+            headers.push(Header::new("Retry-After", retry_after.to_string()));
+        }
 
-pub type RateLimiterMap = SkipMap<IpAddr, ArrayQueue<Timestamp>>;
+        headers
+    }
+}
 
-#[derive(Debug)]
-pub struct RateLimiter {
-    call_queue: Arc<RateLimiterMap>,
-    last_purge: Arc<Mutex<Timestamp>>,
+/// Headers a browser client is allowed to read from rate limited responses.
+pub const RATE_LIMIT_HEADERS: [&str; 4] = [
+    "RateLimit-Limit",
+    "RateLimit-Remaining",
+    "RateLimit-Reset",
+    "Retry-After",
+];
+
+fn nanos_to_secs_ceil(nanos: u64) -> u64 {
+    nanos.div_ceil(NANOS_PER_SEC)
 }
 
-impl Default for RateLimiter {
-    fn default() -> Self {
-        let now = timestamp();
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct RatePolicy {
+    /// Number of requests replenished every `period` seconds.
+    pub requests: u32,
+    /// Replenishment period in seconds.
+    pub period: u64,
+    /// Maximum number of requests that can be issued back to back.
+    pub burst: u32,
+    /// Names of the routes (handler functions) charged against this policy.
+    #[serde(default)]
+    pub routes: Vec<String>,
+}
 
+impl RatePolicy {
+    pub fn new(requests: u32, period: u64, burst: u32) -> Self {
         Self {
-            call_queue: Arc::new(SkipMap::new()),
-            last_purge: Arc::new(Mutex::new(now)),
+            requests,
+            period,
+            burst,
+            routes: vec![],
         }
     }
-}
 
-impl RateLimiter {
-    pub fn new() -> Self {
-        Self::default()
+    fn with_routes(mut self, routes: &[&str]) -> Self {
+        self.routes = routes.iter().map(|route| route.to_string()).collect();
+        self
     }
 
-    fn do_rate_limit(&self, ip: IpAddr) -> Result<()> {
-        self.insert_timestamp(ip)?;
+    /// Time between two replenished requests, in nanoseconds.
+    fn emission_interval(&self) -> u64 {
+        self.period * NANOS_PER_SEC / u64::from(self.requests)
+    }
+
+    fn validate(&self, name: &str) -> Result<(), String> {
+        if self.requests == 0 || self.period == 0 || self.burst == 0 {
+            return Err(format!(
+                "rate limiter policy `{}` must have non-zero requests, period and burst",
+                name
+            ));
+        }
 
         Ok(())
     }
+}
 
-    fn update_queue(&self, key: IpAddr) -> Result<()> {
-        // Clean up the queue
-        self.purge()?;
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde", default)]
+pub struct RateLimiterConfig {
+    /// Policy applied to rate limited routes not listed in any of `policies`.
+    pub default_policy: RatePolicy,
+    pub policies: HashMap<String, RatePolicy>,
+    /// Clients that are never rate limited.
+    pub allowlist: Vec<IpAddr>,
+    /// Seconds a bucket has to stay full before it is evicted.
+    pub idle_timeout: u64,
+    /// Maximum number of buckets inspected for eviction per request.
+    pub eviction_batch: usize,
+}
 
-        // 15 req/minute
-        let result = self.call_queue.get_or_insert(key, ArrayQueue::new(15));
+impl Default for RateLimiterConfig {
+    fn default() -> Self {
+        let policies = HashMap::from([
+            (
+                "compile".to_string(),
+                RatePolicy::new(15, 60, 15).with_routes(&["compile", "compile_async"]),
+            ),
+            (
+                "verify".to_string(),
+                RatePolicy::new(5, 60, 5).with_routes(&[
+                    "verify",
+                    "verify_async",
+                    "verify_batch_async",
+                ]),
+            ),
+        ]);
 
-        let queue = result.value();
+        Self {
+            default_policy: RatePolicy::new(15, 60, 15),
+            policies,
+            allowlist: vec![],
+            idle_timeout: 60 * 60,
+            eviction_batch: 32,
+        }
+    }
+}
 
-        if queue.is_empty() {
-            return Ok(());
+impl RateLimiterConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        self.default_policy.validate(DEFAULT_POLICY_NAME)?;
+        for (name, policy) in self.policies.iter() {
+            policy.validate(name)?;
         }
 
-        let current_time = timestamp();
+        Ok(())
+    }
+}
 
-        while !queue.is_empty() {
-            let time = queue.pop().unwrap();
+/// GCRA (token bucket equivalent) rate limiter keyed by client IP and policy.
+#[derive(Debug)]
+pub struct RateLimiter {
+    config: RateLimiterConfig,
+    route_policies: HashMap<String, String>,
+    buckets: RateLimiterMap,
+    eviction_cursor: Mutex<Option<BucketKey>>,
+    epoch: Instant,
+}
 
-            if current_time - time <= 60 {
-                // 1 minute
-                queue.push(time).map_err(|_| ApiError::QueueIsFull)?;
-                break;
-            }
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(RateLimiterConfig::default())
+    }
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimiterConfig) -> Self {
+        let route_policies = config
+            .policies
+            .iter()
+            .flat_map(|(name, policy)| {
+                policy
+                    .routes
+                    .iter()
+                    .map(move |route| (route.clone(), name.clone()))
+            })
+            .collect();
+
+        Self {
+            config,
+            route_policies,
+            buckets: SkipMap::new(),
+            eviction_cursor: Mutex::new(None),
+            epoch: Instant::now(),
         }
+    }
 
-        Ok(())
+    fn policy_for_route(&self, route: Option<&str>) -> (&str, &RatePolicy) {
+        route
+            .and_then(|route| self.route_policies.get(route))
+            .and_then(|name| {
+                self.config
+                    .policies
+                    .get_key_value(name)
+                    .map(|(name, policy)| (name.as_str(), policy))
+            })
+            .unwrap_or((DEFAULT_POLICY_NAME, &self.config.default_policy))
     }
 
-    fn insert_timestamp(&self, key: IpAddr) -> Result<()> {
-        self.update_queue(key)?;
+    fn now(&self) -> u64 {
+        self.epoch.elapsed().as_nanos() as u64
+    }
 
-        let current_time = timestamp();
+    /// Charges a request from `ip` against the policy of `route`. Allowlisted clients
+    /// are accepted without a decision.
+    pub fn do_rate_limit(
+        &self,
+        ip: IpAddr,
+        route: Option<&str>,
+    ) -> Result<Option<RateLimitDecision>, RateLimitDecision> {
+        self.check_at(ip, route, self.now())
+    }
 
-        let result = self.call_queue.get_or_insert(key, ArrayQueue::new(15));
+    /// Charges the rest of a `batch_size` items request from `ip` against the policy of
+    /// `route`, the `RateLimited` guard having charged the first one. A batch larger than
+    /// the policy's burst is charged as much as the burst, so it can still go through.
+    pub fn charge_batch(
+        &self,
+        ip: IpAddr,
+        route: Option<&str>,
+        batch_size: usize,
+    ) -> Result<Option<RateLimitDecision>, RateLimitDecision> {
+        self.charge_batch_at(ip, route, batch_size, self.now())
+    }
 
-        let queue = result.value();
+    fn charge_batch_at(
+        &self,
+        ip: IpAddr,
+        route: Option<&str>,
+        batch_size: usize,
+        now: u64,
+    ) -> Result<Option<RateLimitDecision>, RateLimitDecision> {
+        let burst = self.policy_for_route(route).1.burst;
+        let cost = u32::try_from(batch_size).unwrap_or(u32::MAX).min(burst);
+        if cost <= 1 {
+            return Ok(None);
+        }
 
-        queue
-            .push(current_time)
-            .map_err(|_| ApiError::QueueIsFull)?;
+        self.charge_at(ip, route, cost - 1, now)
+    }
 
-        Ok(())
+    fn check_at(
+        &self,
+        ip: IpAddr,
+        route: Option<&str>,
+        now: u64,
+    ) -> Result<Option<RateLimitDecision>, RateLimitDecision> {
+        self.charge_at(ip, route, 1, now)
     }
 
-    fn purge(&self) -> Result<()> {
-        let now = timestamp();
+    /// Charges `cost` requests at once.
+    fn charge_at(
+        &self,
+        ip: IpAddr,
+        route: Option<&str>,
+        cost: u32,
+        now: u64,
+    ) -> Result<Option<RateLimitDecision>, RateLimitDecision> {
+        if self.config.allowlist.contains(&ip) {
+            return Ok(None);
+        }
+
+        let (policy_name, policy) = self.policy_for_route(route);
+        let interval = policy.emission_interval();
+        let increment = interval * u64::from(cost);
+        let capacity = interval * u64::from(policy.burst);
+
+        let entry = self
+            .buckets
+            .get_or_insert((ip, policy_name.to_string()), AtomicU64::new(0));
+        let tat = entry.value();
+
+        let mut current = tat.load(Ordering::Acquire);
+        let result = loop {
+            let new_tat = current.max(now) + increment;
+            if new_tat - now > capacity {
+                break Err(RateLimitDecision {
+                    limit: policy.burst,
+                    remaining: 0,
+                    reset: nanos_to_secs_ceil(current.max(now) - now),
+                    retry_after: Some(nanos_to_secs_ceil(new_tat - now - capacity).max(1)),
+                });
+            }
+
+            match tat.compare_exchange_weak(current, new_tat, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => {
+                    break Ok(Some(RateLimitDecision {
+                        limit: policy.burst,
+                        remaining: ((capacity - (new_tat - now)) / interval) as u32,
+                        reset: nanos_to_secs_ceil(new_tat - now),
+                        retry_after: None,
+                    }))
+                }
+                Err(actual) => current = actual,
+            }
+        };
+
+        self.evict_idle(now);
 
-        let mut last_purge = self
-            .last_purge
-            .lock()
-            .map_err(|_| ApiError::MutexUnlockError)?;
+        result
+    }
 
-        if now - *last_purge > 60 * 60 {
-            info!("Purging call queue in the rate limiter");
+    /// Walks at most `eviction_batch` buckets past the cursor and drops those that have been
+    /// full for longer than `idle_timeout`. A full bucket is indistinguishable from a missing
+    /// one, so eviction never changes a client's budget.
+    fn evict_idle(&self, now: u64) {
+        // Another request is already sweeping
+        let Ok(mut cursor) = self.eviction_cursor.try_lock() else {
+            return;
+        };
 
-            self.call_queue.clear();
+        let idle_timeout = self.config.idle_timeout * NANOS_PER_SEC;
+        let lower = match cursor.take() {
+            Some(key) => Bound::Excluded(key),
+            None => Bound::Unbounded,
+        };
 
-            *last_purge = now;
+        let mut visited = 0;
+        let mut last_key = None;
+        for entry in self
+            .buckets
+            .range((lower, Bound::Unbounded))
+            .take(self.config.eviction_batch)
+        {
+            visited += 1;
+            if entry.value().load(Ordering::Acquire) + idle_timeout < now {
+                entry.remove();
+            }
+            last_key = Some(entry.key().clone());
         }
 
-        Ok(())
+        // Restart from the beginning once the end of the map is reached
+        if visited == self.config.eviction_batch {
+            *cursor = last_key;
+        }
     }
 }
 
@@ -123,14 +368,124 @@ impl<'r> FromRequest<'r> for RateLimited {
             Some(x) => x,
         };
 
-        let client_ip = match request.client_ip() {
+        let client_ip = match ClientIp::resolve(request) {
             None => return Outcome::Error((Status::BadRequest, ApiError::FailedToGetClientIp)),
-            Some(x) => x,
+            Some(ClientIp(x)) => x,
         };
 
-        match rate_limiter.do_rate_limit(client_ip) {
-            Ok(_) => Outcome::Success(RateLimited),
-            Err(_) => Outcome::Error((Status::TooManyRequests, ApiError::TooManyRequests)),
+        let route = request.route().and_then(|route| route.name.as_deref());
+
+        match rate_limiter.do_rate_limit(client_ip, route) {
+            Ok(decision) => {
+                request.local_cache(|| decision);
+                Outcome::Success(RateLimited)
+            }
+            Err(decision) => {
+                request.local_cache(|| Some(decision));
+                Outcome::Error((Status::TooManyRequests, ApiError::TooManyRequests))
+            }
+        }
+    }
+}
+
+/// Adds the `RateLimit-*` and `Retry-After` headers for requests that went through
+/// the `RateLimited` guard.
+#[derive(Default)]
+pub struct RateLimitHeaders;
+
+#[rocket::async_trait]
+impl Fairing for RateLimitHeaders {
+    fn info(&self) -> Info {
+        Info {
+            name: "Add rate limit headers to responses",
+            kind: Kind::Response,
         }
     }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let decision: &Option<RateLimitDecision> = request.local_cache(|| None);
+        if let Some(decision) = decision {
+            for header in decision.headers() {
+                response.set_header(header);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_gcra_burst_and_per_route_policies() {
+    use std::net::Ipv4Addr;
+
+    let client = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    let limiter = RateLimiter::new(RateLimiterConfig {
+        allowlist: vec![IpAddr::V4(Ipv4Addr::LOCALHOST)],
+        ..RateLimiterConfig::default()
+    });
+
+    for _ in 0..5 {
+        assert!(limiter.check_at(client, Some("verify"), 0).is_ok());
+    }
+    // `verify_async` shares the stricter verify budget, compile has its own
+    assert!(limiter.check_at(client, Some("verify_async"), 0).is_err());
+    assert!(limiter.check_at(client, Some("compile"), 0).is_ok());
+
+    assert_eq!(
+        limiter.check_at(client, Some("verify"), NANOS_PER_SEC),
+        Err(RateLimitDecision {
+            limit: 5,
+            remaining: 0,
+            reset: 59,
+            retry_after: Some(11),
+        })
+    );
+
+    // One verify request is replenished every 12 seconds
+    assert!(limiter
+        .check_at(client, Some("verify"), 12 * NANOS_PER_SEC)
+        .is_ok());
+    assert!(limiter
+        .check_at(client, Some("verify"), 12 * NANOS_PER_SEC)
+        .is_err());
+
+    // A batch is charged per contract, up to the burst
+    let batch_client = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+    assert!(limiter
+        .check_at(batch_client, Some("verify_batch_async"), 0)
+        .is_ok());
+    assert!(limiter
+        .charge_batch_at(batch_client, Some("verify_batch_async"), 3, 0)
+        .is_ok());
+    assert_eq!(
+        limiter
+            .charge_batch_at(batch_client, Some("verify_batch_async"), 4, 0)
+            .unwrap_err()
+            .retry_after,
+        Some(12)
+    );
+    assert!(limiter.check_at(batch_client, Some("verify"), 0).is_ok());
+    assert!(limiter.check_at(batch_client, Some("verify"), 0).is_ok());
+    assert!(limiter.check_at(batch_client, Some("verify"), 0).is_err());
+    let big_batch_client = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3));
+    assert!(limiter
+        .check_at(big_batch_client, Some("verify_batch_async"), 0)
+        .is_ok());
+    assert!(limiter
+        .charge_batch_at(big_batch_client, Some("verify_batch_async"), 20, 0)
+        .is_ok());
+
+    for _ in 0..100 {
+        assert!(limiter
+            .check_at(IpAddr::V4(Ipv4Addr::LOCALHOST), Some("verify"), 0)
+            .is_ok());
+    }
+
+    // Every bucket has been full for longer than the idle timeout
+    limiter
+        .check_at(
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            None,
+            7200 * NANOS_PER_SEC,
+        )
+        .unwrap();
+    assert_eq!(limiter.buckets.len(), 1);
 }
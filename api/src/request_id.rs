@@ -0,0 +1,80 @@
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::Header;
+use rocket::request::{FromRequest, Outcome};
+use rocket::{Data, Request, Response};
+use std::convert::Infallible;
+use std::fmt::{Display, Formatter};
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Identifier tying together the logs, spans, jobs and responses of a single request.
+/// Taken from the `X-Request-Id` header when it is well formed, generated otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    fn is_valid(id: &str) -> bool {
+        !id.is_empty()
+            && id.len() <= MAX_REQUEST_ID_LEN
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    }
+
+    pub fn from_request<'r>(request: &'r Request<'_>) -> &'r RequestId {
+        request.local_cache(|| match request.headers().get_one(REQUEST_ID_HEADER) {
+            Some(id) if RequestId::is_valid(id) => RequestId(id.to_string()),
+            _ => RequestId(Uuid::new_v4().to_string()),
+        })
+    }
+}
+
+impl Display for RequestId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RequestId {
+    type Error = Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(RequestId::from_request(request).clone())
+    }
+}
+
+/// Assigns a request id as soon as a request comes in and echoes it in the response.
+#[derive(Default)]
+pub struct RequestIdFairing;
+
+#[rocket::async_trait]
+impl Fairing for RequestIdFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "Assign request ids",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, req: &mut Request<'_>, _data: &mut Data<'_>) {
+        RequestId::from_request(req);
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let request_id = RequestId::from_request(request);
+        response.set_header(Header::new(REQUEST_ID_HEADER, request_id.0.clone()));
+    }
+}
+
+#[test]
+fn test_request_id_validation() {
+    assert!(RequestId::is_valid("3f2c9a8e-1b7d-4c1e-9f3a-2d4b6e8a0c12"));
+    assert!(RequestId::is_valid("req_42.retry"));
+    assert!(!RequestId::is_valid(""));
+    assert!(!RequestId::is_valid("id\r\ninjected: header"));
+    assert!(!RequestId::is_valid(&"a".repeat(MAX_REQUEST_ID_LEN + 1)));
+}
@@ -1,13 +1,24 @@
-use tracing_appender::rolling;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::SpanExporter;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
 use tracing_subscriber::field::MakeExt;
-use tracing_subscriber::fmt::writer::MakeWriterExt;
 use tracing_subscriber::registry::LookupSpan;
-use tracing_subscriber::Layer;
 use tracing_subscriber::{prelude::*, EnvFilter};
+use tracing_subscriber::{Layer, Registry};
 use yansi::Paint;
 
 use crate::errors::CoreError;
 
+const DEFAULT_SERVICE_NAME: &str = "zksync-remix-api";
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde", from = "String", rename_all = "lowercase")]
 pub enum LogType {
     Formatted,
     Json,
@@ -18,7 +29,102 @@ impl From<String> for LogType {
         match input.as_str() {
             "formatted" => Self::Formatted,
             "json" => Self::Json,
-            _ => panic!("Unkown log type {}", input),
+            _ => {
+                // The logger isn't up yet, so this can only go to stderr
+                eprintln!("Unknown log type {}, falling back to json", input);
+                Self::Json
+            }
+        }
+    }
+}
+
+/// How often the log files are rolled over.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde", from = "String", rename_all = "lowercase")]
+pub enum LogRotation {
+    Minutely,
+    Hourly,
+    Daily,
+    Never,
+}
+
+impl From<String> for LogRotation {
+    fn from(input: String) -> Self {
+        match &*input.to_ascii_lowercase() {
+            "minutely" => Self::Minutely,
+            "hourly" => Self::Hourly,
+            "daily" => Self::Daily,
+            "never" => Self::Never,
+            _ => {
+                eprintln!("Unknown log rotation {}, falling back to daily", input);
+                Self::Daily
+            }
+        }
+    }
+}
+
+impl From<LogRotation> for Rotation {
+    fn from(rotation: LogRotation) -> Self {
+        match rotation {
+            LogRotation::Minutely => Rotation::MINUTELY,
+            LogRotation::Hourly => Rotation::HOURLY,
+            LogRotation::Daily => Rotation::DAILY,
+            LogRotation::Never => Rotation::NEVER,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct LogFileConfig {
+    /// File name prefix, the rotation date is appended to it.
+    pub prefix: String,
+    pub level: LogLevel,
+    #[serde(default = "LogFileConfig::default_format")]
+    pub format: LogType,
+}
+
+impl LogFileConfig {
+    fn default_format() -> LogType {
+        LogType::Json
+    }
+
+    fn new(prefix: &str, level: LogLevel) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            level,
+            format: Self::default_format(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde", default)]
+pub struct LoggingConfig {
+    /// Console output format, overridden by `LOG_TYPE`.
+    pub format: LogType,
+    /// Console log level, overridden by `LOG_LEVEL`.
+    pub level: LogLevel,
+    pub directory: PathBuf,
+    pub rotation: LogRotation,
+    /// Rotated files kept per log file, older ones are deleted. `0` keeps all of them.
+    pub max_files: usize,
+    pub files: Vec<LogFileConfig>,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            format: LogType::Json,
+            level: LogLevel::Normal,
+            directory: PathBuf::from("./logs"),
+            rotation: LogRotation::Daily,
+            max_files: 7,
+            files: vec![
+                LogFileConfig::new("debug", LogLevel::Debug),
+                LogFileConfig::new("info", LogLevel::Normal),
+                LogFileConfig::new("warnings", LogLevel::Critical),
+            ],
         }
     }
 }
@@ -60,7 +166,8 @@ pub fn json_logging_layer<
         .with_test_writer()
 }
 
-#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde", from = "String", rename_all = "lowercase")]
 pub enum LogLevel {
     /// Only shows errors and warnings: `"critical"`.
     Critical,
@@ -83,11 +190,24 @@ impl From<&str> for LogLevel {
             "normal" => LogLevel::Normal,
             "debug" => LogLevel::Debug,
             "off" => LogLevel::Off,
-            _ => panic!("a log level (off, debug, normal, support, critical)"),
+            _ => {
+                eprintln!(
+                    "Unknown log level {}, expected one of off, debug, normal, support, critical. \
+                    Falling back to normal",
+                    s
+                );
+                LogLevel::Normal
+            }
         }
     }
 }
 
+impl From<String> for LogLevel {
+    fn from(s: String) -> Self {
+        LogLevel::from(s.as_str())
+    }
+}
+
 pub fn filter_layer(level: LogLevel) -> EnvFilter {
     let filter_str = match level {
         LogLevel::Critical => "warn,hyper=off,rustls=off",
@@ -100,41 +220,132 @@ pub fn filter_layer(level: LogLevel) -> EnvFilter {
     EnvFilter::try_new(filter_str).expect("filter string must parse")
 }
 
-pub fn init_logger() -> Result<(), CoreError> {
-    // Log all `tracing` events to files prefixed with `debug`.
-    // Rolling these files every day
-    let debug_file = rolling::daily("./logs", "debug").with_max_level(tracing::Level::TRACE);
-    // Log warnings and errors to a separate file. Since we expect these events
-    // to occur less frequently, roll that file on a daily basis
-    let warn_file = rolling::daily("./logs", "warnings").with_max_level(tracing::Level::WARN);
-    let info_file = rolling::daily("./logs", "info").with_max_level(tracing::Level::INFO);
-    let all_files = debug_file.and(warn_file).and(info_file);
+/// Keeps the OpenTelemetry pipeline alive, pending spans are flushed when it is dropped.
+#[derive(Default)]
+pub struct TracingGuard {
+    tracer_provider: Option<SdkTracerProvider>,
+}
+
+impl Drop for TracingGuard {
+    fn drop(&mut self) {
+        if let Some(tracer_provider) = self.tracer_provider.take() {
+            if let Err(e) = tracer_provider.shutdown() {
+                eprintln!(
+                    "Failed to shut down the OpenTelemetry tracer provider: {}",
+                    e
+                );
+            }
+        }
+    }
+}
+
+/// Builds the OTLP/HTTP span exporter pipeline when a collector endpoint is configured.
+/// Endpoint, headers and timeout are read by the exporter from the standard
+/// `OTEL_EXPORTER_OTLP_*` environment variables.
+pub fn otlp_tracer_provider() -> Result<Option<SdkTracerProvider>, CoreError> {
+    if std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").is_err()
+        && std::env::var("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT").is_err()
+    {
+        return Ok(None);
+    }
+
+    let exporter = SpanExporter::builder().with_http().build()?;
+    let service_name =
+        std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| DEFAULT_SERVICE_NAME.to_string());
+    let resource = Resource::builder().with_service_name(service_name).build();
 
-    let rolling_files = tracing_subscriber::fmt::layer()
-        .json()
-        .with_writer(all_files)
-        .with_ansi(false)
-        .with_filter(filter_layer(LogLevel::Debug));
-
-    let log_type = LogType::from(std::env::var("LOG_TYPE").unwrap_or_else(|_| "json".to_string()));
-    let log_level = LogLevel::from(
-        std::env::var("LOG_LEVEL")
-            .unwrap_or_else(|_| "normal".to_string())
-            .as_str(),
-    );
-
-    match log_type {
-        LogType::Formatted => tracing::subscriber::set_global_default(
-            tracing_subscriber::registry()
-                .with(default_logging_layer())
-                .with(filter_layer(log_level)),
-        )?,
-        LogType::Json => tracing::subscriber::set_global_default(
-            tracing_subscriber::registry()
-                .with(json_logging_layer().with_filter(filter_layer(log_level)))
-                .with(rolling_files),
-        )?,
+    Ok(Some(
+        SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(resource)
+            .build(),
+    ))
+}
+
+pub fn otel_layer<S>(tracer_provider: &SdkTracerProvider) -> impl Layer<S>
+where
+    S: tracing::Subscriber,
+    S: for<'span> LookupSpan<'span>,
+{
+    tracing_opentelemetry::layer().with_tracer(tracer_provider.tracer(DEFAULT_SERVICE_NAME))
+}
+
+fn file_layer(config: &LoggingConfig, file: &LogFileConfig) -> Result<BoxedLayer, CoreError> {
+    let appender = RollingFileAppender::builder()
+        .rotation(config.rotation.into())
+        .filename_prefix(&file.prefix)
+        .max_log_files(config.max_files)
+        .build(&config.directory)?;
+
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(appender)
+        .with_ansi(false);
+    let layer = match file.format {
+        LogType::Formatted => layer.with_filter(filter_layer(file.level)).boxed(),
+        LogType::Json => layer.json().with_filter(filter_layer(file.level)).boxed(),
     };
 
-    Ok(())
+    Ok(layer)
+}
+
+pub fn init_logger(config: &LoggingConfig) -> Result<TracingGuard, CoreError> {
+    let log_level = config.level;
+
+    let mut layers: Vec<BoxedLayer> = vec![match config.format {
+        LogType::Formatted => default_logging_layer()
+            .with_filter(filter_layer(log_level))
+            .boxed(),
+        LogType::Json => json_logging_layer()
+            .with_filter(filter_layer(log_level))
+            .boxed(),
+    }];
+
+    if !config.files.is_empty() {
+        // Created upfront, the appenders try to prune it before creating it themselves
+        std::fs::create_dir_all(&config.directory).map_err(|e| {
+            CoreError::InvalidConfig(format!(
+                "failed to create log directory {}: {}",
+                config.directory.display(),
+                e
+            ))
+        })?;
+    }
+    for file in &config.files {
+        layers.push(file_layer(config, file)?);
+    }
+
+    let tracer_provider = otlp_tracer_provider()?;
+    if let Some(provider) = &tracer_provider {
+        layers.push(
+            otel_layer(provider)
+                .with_filter(filter_layer(log_level))
+                .boxed(),
+        );
+    }
+
+    tracing::subscriber::set_global_default(tracing_subscriber::registry().with(layers))?;
+
+    Ok(TracingGuard { tracer_provider })
+}
+
+#[test]
+fn test_invalid_logging_values_fall_back() {
+    use rocket::figment::providers::{Format, Toml};
+    use rocket::figment::Figment;
+
+    let config: LoggingConfig = Figment::from(Toml::string(
+        r#"
+        level = "verbose"
+        rotation = "fortnightly"
+        files = [{ prefix = "debug", level = "debug", format = "xml" }]
+        "#,
+    ))
+    .extract()
+    .unwrap();
+
+    assert_eq!(config.level, LogLevel::Normal);
+    assert_eq!(config.rotation, LogRotation::Daily);
+    assert_eq!(config.files[0].level, LogLevel::Debug);
+    assert_eq!(config.files[0].format, LogType::Json);
+    assert_eq!(config.directory, PathBuf::from("./logs"));
 }
@@ -1,6 +1,13 @@
+use crate::compiler_registry::{compiler_registry, CompilerKind};
+use crate::config::app_config;
+use crate::errors::ApiError;
+use crate::handlers::types::CompilationConfig;
+use crate::networks::NetworkConfig;
 use crate::utils::lib::{DEFAULT_SOLIDITY_VERSION, DEFAULT_ZKSOLC_VERSION};
+use crate::verification::OptimizerSettings;
 use rocket::serde::json::serde_json;
 use std::fmt::Formatter;
+use std::path::Path;
 
 const DEFAULT_CONTRACTS_LOCATION: &str = "./contracts";
 
@@ -9,17 +16,30 @@ pub struct HardhatConfig {
     pub zksolc: ZksolcConfig,
     pub solidity: SolidityConfig,
     pub paths: ProjectPathsUserConfig,
+    /// Rendered next to hardhat's own network, the first one being the default network.
+    pub networks: Vec<NetworkConfig>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
 pub struct ZksolcConfig {
     pub version: String,
+    /// `"binary"` to use the zksolc at `settings.compilerPath` instead of downloading one.
+    pub compiler_source: Option<String>,
     pub settings: serde_json::Value,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
 pub struct SolidityConfig {
     pub version: String,
+    /// Local solc handed to hardhat instead of the one it would download.
+    pub local_build: Option<SolcBuild>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct SolcBuild {
+    pub compiler_path: String,
+    /// Version with its commit, e.g. `0.8.24+commit.e11b9ed9`.
+    pub long_version: String,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
@@ -29,7 +49,7 @@ pub struct ProjectPathsUserConfig {
 
 impl std::fmt::Display for ProjectPathsUserConfig {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, r#"{{sources: "{}",}}"#, self.sources)
+        write!(f, "{{sources: {}}}", serde_json::json!(self.sources))
     }
 }
 
@@ -51,12 +71,15 @@ impl Default for HardhatConfig {
         Self {
             zksolc: ZksolcConfig {
                 version: DEFAULT_ZKSOLC_VERSION.to_string(),
+                compiler_source: None,
                 settings: serde_json::json!({}),
             },
             solidity: SolidityConfig {
                 version: DEFAULT_SOLIDITY_VERSION.to_string(),
+                local_build: None,
             },
             paths: ProjectPathsUserConfig::default(),
+            networks: app_config().networks.clone(),
         }
     }
 }
@@ -72,52 +95,79 @@ import { HardhatUserConfig } from "hardhat/config";
 
 import "@matterlabs/hardhat-zksync-solc";
 import "@matterlabs/hardhat-zksync-verify";
-
-export const zkSyncTestnet = process.env.NODE_ENV == "test"
-? {
-    url: "http://127.0.0.1:8011",
-    ethNetwork: "http://127.0.0.1:8045",
-    zksync: true,
-  }
-: {
-    url: "https://sepolia.era.zksync.dev",
-    ethNetwork: "sepolia",
-    zksync: true,
-    verifyURL: "https://explorer.sepolia.era.zksync.dev/contract_verification"
-  };
-
-export const zkSyncMainnet = {
-    url: "https://mainnet.era.zksync.io",
-    ethNetwork: "mainnet",
-    zksync: true,
-    verifyURL: "https://zksync2-mainnet-explorer.zksync.io/contract_verification"
-  };
 "#;
 
+        // hardhat resolves solc through this subtask, zksolc is then given the same binary
+        let local_solc = self
+            .solidity
+            .local_build
+            .as_ref()
+            .map(|build| {
+                format!(
+                    r#"
+import {{ subtask }} from "hardhat/config";
+import {{ TASK_COMPILE_SOLIDITY_GET_SOLC_BUILD }} from "hardhat/builtin-tasks/task-names";
+
+subtask(TASK_COMPILE_SOLIDITY_GET_SOLC_BUILD, async (args: {{ solcVersion: string }}, hre, runSuper) => {{
+  if (args.solcVersion === {}) {{
+    return {{ compilerPath: {}, isSolcJs: false, version: args.solcVersion, longVersion: {} }};
+  }}
+  return runSuper();
+}});
+"#,
+                    serde_json::json!(self.solidity.version),
+                    serde_json::json!(build.compiler_path),
+                    serde_json::json!(build.long_version),
+                )
+            })
+            .unwrap_or_default();
+
+        let compiler_source = self
+            .zksolc
+            .compiler_source
+            .as_ref()
+            .map(|source| format!("\n    compilerSource: {},", serde_json::json!(source)))
+            .unwrap_or_default();
+
+        let mut networks = serde_json::Map::new();
+        networks.insert(
+            "hardhat".to_string(),
+            serde_json::json!({ "zksync": false }),
+        );
+        for network in &self.networks {
+            networks.insert(network.name.clone(), network.to_hardhat_network());
+        }
+        let default_network = self
+            .networks
+            .first()
+            .map_or("hardhat", |network| network.name.as_str());
+
         let config = format!(
-            r#"{}
+            r#"{}{}
 const config: HardhatUserConfig = {{
   zksolc: {{
-    version: "{}",
-    settings: {{}},
-  }},
-  defaultNetwork: "zkSyncTestnet",
-  networks: {{
-    hardhat: {{
-      zksync: false,
-    }},
-    zkSyncTestnet,
-    zkSyncMainnet,
+    version: {},{}
+    settings: {},
   }},
+  defaultNetwork: {},
+  networks: {},
   solidity: {{
-    version: "{}",
+    version: {},
   }},
   paths: {},
 }};
 
 export default config;
 "#,
-            config_prefix_js, self.zksolc.version, self.solidity.version, self.paths
+            config_prefix_js,
+            local_solc,
+            serde_json::json!(self.zksolc.version),
+            compiler_source,
+            self.zksolc.settings,
+            serde_json::json!(default_network),
+            serde_json::Value::Object(networks),
+            serde_json::json!(self.solidity.version),
+            self.paths
         );
 
         config
@@ -129,16 +179,81 @@ impl HardhatConfigBuilder {
         Self::default()
     }
 
+    /// Builder compiling the way `config` asks for, with the sources under `target_path`.
+    /// Compile and verify both start from it, so that verification sees the same bytecode.
+    pub fn from_compilation(
+        config: &CompilationConfig,
+        target_path: Option<&str>,
+    ) -> Result<Self, ApiError> {
+        let mut builder = Self::new();
+        builder
+            .zksolc_version(&config.version)
+            .solidity_version(
+                config
+                    .solc_version
+                    .as_deref()
+                    .unwrap_or(DEFAULT_SOLIDITY_VERSION),
+            )
+            .optimizer(&config.optimizer);
+        if let Some(zksolc) = compiler_registry().get(CompilerKind::Zksolc, &config.version) {
+            builder.zksolc_compiler_path(&zksolc.path);
+        }
+        if let Some(solc) =
+            compiler_registry().get(CompilerKind::Solc, &builder.config.solidity.version)
+        {
+            builder.solc_compiler_path(&solc.path, &solc.long_version());
+        }
+
+        let libraries = config.libraries()?;
+        if !libraries.is_empty() {
+            builder.config.zksolc.settings["libraries"] = serde_json::json!(libraries);
+        }
+        if let Some(target_path) = target_path {
+            builder.paths_sources(target_path);
+        }
+
+        Ok(builder)
+    }
+
     pub fn zksolc_version(&mut self, version: &str) -> &mut Self {
         self.config.zksolc.version = version.to_string();
         self
     }
 
+    /// Compiles with the zksolc binary at `path` rather than letting the plugin download it.
+    pub fn zksolc_compiler_path(&mut self, path: &Path) -> &mut Self {
+        self.config.zksolc.compiler_source = Some("binary".to_string());
+        self.config.zksolc.settings["compilerPath"] = path.to_string_lossy().into();
+        self
+    }
+
+    /// Compiles with the solc binary at `path` rather than letting hardhat download it.
+    pub fn solc_compiler_path(&mut self, path: &Path, long_version: &str) -> &mut Self {
+        self.config.solidity.local_build = Some(SolcBuild {
+            compiler_path: path.to_string_lossy().into(),
+            long_version: long_version.to_string(),
+        });
+        self
+    }
+
+    /// Left out when it matches zksolc's defaults.
+    pub fn optimizer(&mut self, optimizer: &OptimizerSettings) -> &mut Self {
+        if *optimizer != OptimizerSettings::default() {
+            self.config.zksolc.settings["optimizer"] = serde_json::json!(optimizer);
+        }
+        self
+    }
+
     pub fn solidity_version(&mut self, version: &str) -> &mut Self {
         self.config.solidity.version = version.to_string();
         self
     }
 
+    pub fn networks(&mut self, networks: &[NetworkConfig]) -> &mut Self {
+        self.config.networks = networks.to_vec();
+        self
+    }
+
     pub fn paths_sources(&mut self, target_path: &str) -> &mut Self {
         self.config.paths.sources = target_path.to_string();
         self
@@ -161,3 +276,69 @@ fn test_paths_user_config_display() {
 
     assert_eq!(expected, actual);
 }
+
+#[test]
+fn test_config_from_compilation() {
+    crate::config::init_test_app_config();
+
+    let mut config = CompilationConfig {
+        version: "1.5.6".to_string(),
+        user_libraries: vec![
+            "contracts/Math.sol:Math=0x00000000000000000000000000000000000000aa".to_string(),
+        ],
+        solc_version: Some("0.8.24".to_string()),
+        optimizer: OptimizerSettings {
+            enabled: true,
+            mode: "z".to_string(),
+        },
+    };
+
+    let hardhat_config = HardhatConfigBuilder::from_compilation(&config, Some("./contracts/src"))
+        .unwrap()
+        .build();
+    assert_eq!(hardhat_config.paths.sources, "./contracts/src");
+    assert_eq!(hardhat_config.solidity.version, "0.8.24");
+    assert_eq!(hardhat_config.zksolc.settings["optimizer"]["mode"], "z");
+    assert_eq!(
+        hardhat_config.zksolc.settings["libraries"]["contracts/Math.sol"]["Math"],
+        "0x00000000000000000000000000000000000000aa"
+    );
+
+    let hardhat_config = HardhatConfigBuilder::from_compilation(&config, None)
+        .unwrap()
+        .solc_compiler_path(
+            Path::new("/compilers/solc-v0.8.24"),
+            "0.8.24+commit.e11b9ed9",
+        )
+        .build()
+        .to_string_config();
+    assert!(hardhat_config.contains(r#"if (args.solcVersion === "0.8.24")"#));
+    assert!(hardhat_config.contains(
+        r#"compilerPath: "/compilers/solc-v0.8.24", isSolcJs: false, version: args.solcVersion, longVersion: "0.8.24+commit.e11b9ed9""#
+    ));
+
+    config.user_libraries = vec!["Math=0xaa".to_string()];
+    assert!(matches!(
+        HardhatConfigBuilder::from_compilation(&config, None),
+        Err(ApiError::InvalidLibrary(_))
+    ));
+}
+
+#[test]
+fn test_config_escapes_injected_versions() {
+    crate::config::init_test_app_config();
+
+    let injected = r#"0.8.24" }, x: require("child_process").execSync("id"), y: { a: ""#;
+    let hardhat_config = HardhatConfigBuilder::new()
+        .solidity_version(injected)
+        .paths_sources(injected)
+        .build()
+        .to_string_config();
+
+    let escaped = serde_json::json!(injected).to_string();
+    assert!(hardhat_config.contains(&format!(
+        "    version: {},\n  }},\n  paths: {{sources: {}}},",
+        escaped, escaped
+    )));
+    assert!(!hardhat_config.contains(r#"x: require("child_process")"#));
+}
@@ -0,0 +1,71 @@
+use std::hash::{BuildHasher, Hash};
+
+/// Number of index bits, 2^12 registers give a standard error of ~1.6%.
+const PRECISION: u32 = 12;
+const NUM_REGISTERS: usize = 1 << PRECISION;
+
+/// HyperLogLog distinct-count estimator. Only the maximum leading-zero run per register
+/// is kept, so the original items can't be recovered from the sketch.
+#[derive(Debug, Clone)]
+pub struct HyperLogLog<S: BuildHasher> {
+    registers: Vec<u8>,
+    hasher: S,
+}
+
+impl<S: BuildHasher> HyperLogLog<S> {
+    pub fn with_hasher(hasher: S) -> Self {
+        Self {
+            registers: vec![0; NUM_REGISTERS],
+            hasher,
+        }
+    }
+
+    pub fn insert<T: Hash>(&mut self, item: &T) {
+        let hash = self.hasher.hash_one(item);
+        let index = (hash >> (64 - PRECISION)) as usize;
+        // Rank of the first set bit in the remaining bits, the sentinel bit caps it
+        let rank = ((hash << PRECISION) | (1 << (PRECISION - 1))).leading_zeros() + 1;
+
+        let register = &mut self.registers[index];
+        *register = (*register).max(rank as u8);
+    }
+
+    pub fn estimate(&self) -> f64 {
+        let m = NUM_REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|&register| 2f64.powi(-i32::from(register)))
+            .sum();
+        let estimate = alpha * m * m / sum;
+
+        // Linear counting is more accurate for small cardinalities
+        let zeros = self
+            .registers
+            .iter()
+            .filter(|&&register| register == 0)
+            .count();
+        if estimate <= 2.5 * m && zeros > 0 {
+            return m * (m / zeros as f64).ln();
+        }
+
+        estimate
+    }
+}
+
+#[test]
+fn test_estimate_error() {
+    use std::collections::hash_map::RandomState;
+
+    let mut hll = HyperLogLog::with_hasher(RandomState::new());
+    for i in 0..50_000u32 {
+        // Duplicates must not be counted twice
+        hll.insert(&i);
+        hll.insert(&i);
+    }
+
+    let error = (hll.estimate() - 50_000.0).abs() / 50_000.0;
+    assert!(error < 0.05, "estimate is off by {:.2}%", error * 100.0);
+}
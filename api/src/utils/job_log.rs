@@ -0,0 +1,149 @@
+use chrono::{SecondsFormat, Utc};
+use rocket::tokio;
+use rocket::tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::process::Output;
+use std::sync::Mutex;
+use tokio::process::Child;
+
+use crate::errors::ApiError;
+
+/// Lines kept per job, the oldest ones are dropped past this.
+const MAX_JOB_LOG_LINES: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(crate = "rocket::serde", rename_all = "lowercase")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct JobLogLine {
+    pub timestamp: String,
+    pub stream: LogStream,
+    pub line: String,
+}
+
+#[derive(Debug, Default)]
+struct JobLogLines {
+    lines: VecDeque<JobLogLine>,
+    /// Lines dropped from the front, keeps offsets stable once the buffer is full.
+    dropped: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct JobLogPage {
+    /// Offset of the first returned line.
+    pub offset: usize,
+    /// Offset to pass to get the lines logged after this page.
+    pub next_offset: usize,
+    pub lines: Vec<JobLogLine>,
+}
+
+/// Output of the commands spawned for a job, in the order it was received.
+#[derive(Debug, Default)]
+pub struct JobLog {
+    inner: Mutex<JobLogLines>,
+}
+
+impl JobLog {
+    pub fn push(&self, stream: LogStream, line: String) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.lines.len() == MAX_JOB_LOG_LINES {
+            inner.lines.pop_front();
+            inner.dropped += 1;
+        }
+
+        inner.lines.push_back(JobLogLine {
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            stream,
+            line,
+        });
+    }
+
+    /// Lines from `offset` on, limited to the last `tail` of them if given.
+    pub fn page(&self, offset: Option<usize>, tail: Option<usize>) -> JobLogPage {
+        let inner = self.inner.lock().unwrap();
+        let next_offset = inner.dropped + inner.lines.len();
+
+        let mut start = offset.unwrap_or(0).clamp(inner.dropped, next_offset);
+        if let Some(tail) = tail {
+            start = start.max(next_offset.saturating_sub(tail));
+        }
+
+        JobLogPage {
+            offset: start,
+            next_offset,
+            lines: inner
+                .lines
+                .range(start - inner.dropped..)
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+async fn capture_stream<R: AsyncRead + Unpin>(
+    reader: Option<R>,
+    stream: LogStream,
+    job_log: Option<&JobLog>,
+) -> std::io::Result<Vec<u8>> {
+    let mut output = vec![];
+    let Some(reader) = reader else {
+        return Ok(output);
+    };
+
+    let mut reader = BufReader::new(reader);
+    let mut line = vec![];
+    while reader.read_until(b'\n', &mut line).await? > 0 {
+        if let Some(job_log) = job_log {
+            let text = String::from_utf8_lossy(&line);
+            job_log.push(stream, text.trim_end_matches(['\n', '\r']).to_string());
+        }
+        output.append(&mut line);
+    }
+
+    Ok(output)
+}
+
+/// Same as [`Child::wait_with_output`], additionally recording each line written to the
+/// piped stdout and stderr into `job_log` as it arrives.
+pub async fn wait_with_captured_output(
+    mut child: Child,
+    job_log: Option<&JobLog>,
+) -> Result<Output, ApiError> {
+    let stdout = capture_stream(child.stdout.take(), LogStream::Stdout, job_log);
+    let stderr = capture_stream(child.stderr.take(), LogStream::Stderr, job_log);
+
+    let (stdout, stderr, status) = tokio::join!(stdout, stderr, child.wait());
+
+    Ok(Output {
+        status: status.map_err(ApiError::FailedToReadOutput)?,
+        stdout: stdout.map_err(ApiError::FailedToReadOutput)?,
+        stderr: stderr.map_err(ApiError::FailedToReadOutput)?,
+    })
+}
+
+#[test]
+fn test_job_log_paging() {
+    let job_log = JobLog::default();
+    for i in 0..MAX_JOB_LOG_LINES + 5 {
+        job_log.push(LogStream::Stdout, i.to_string());
+    }
+
+    let page = job_log.page(None, None);
+    assert_eq!(page.offset, 5);
+    assert_eq!(page.lines[0].line, "5");
+    assert_eq!(page.next_offset, MAX_JOB_LOG_LINES + 5);
+
+    let page = job_log.page(Some(MAX_JOB_LOG_LINES), Some(2));
+    assert_eq!(page.offset, MAX_JOB_LOG_LINES + 3);
+    assert_eq!(page.lines.len(), 2);
+
+    let page = job_log.page(Some(MAX_JOB_LOG_LINES + 5), None);
+    assert!(page.lines.is_empty());
+}
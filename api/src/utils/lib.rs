@@ -1,5 +1,6 @@
 use crate::errors::{ApiError, Result};
 use crate::handlers::types::{CompilationConfig, CompilationRequest, CompiledFile};
+use crate::verification::OptimizerSettings;
 use rocket::tokio;
 use rocket::tokio::fs;
 use solang_parser::diagnostics::{Diagnostic, ErrorType, Level};
@@ -16,26 +17,22 @@ pub const ZK_CACHE_ROOT: &str = concat!(
 );
 pub const HARDHAT_ENV_ROOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/", "hardhat_env/");
 
+pub const COMPILERS_ROOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/", "hardhat_env/compilers");
+
 pub const ARTIFACTS_ROOT: &str =
     concat!(env!("CARGO_MANIFEST_DIR"), "/", "hardhat_env/artifacts-zk");
 
 pub const CARGO_MANIFEST_DIR: &str = env!("CARGO_MANIFEST_DIR");
 
-pub const DURATION_TO_PURGE: u64 = 60 * 5; // 5 minutes
-
-pub const ZKSOLC_VERSIONS: [&str; 4] = ["1.5.6", "1.5.5", "1.4.1", "1.4.0"];
-
 pub const DEFAULT_SOLIDITY_VERSION: &str = "0.8.24";
 
 pub const DEFAULT_ZKSOLC_VERSION: &str = "1.5.6";
 
-pub const ALLOWED_NETWORKS: [&str; 2] = ["sepolia", "mainnet"];
-
 #[allow(dead_code)]
 pub const TEMP_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/", "temp/");
 
 pub fn get_file_ext(file_path: &str) -> String {
-    match file_path.split('.').last() {
+    match file_path.split('.').next_back() {
         Some(ext) => ext.to_string(),
         None => {
             debug!("LOG: File extension not found");
@@ -194,11 +191,24 @@ pub fn list_files_in_directory<P: AsRef<Path>>(path: P) -> Vec<String> {
     file_paths
 }
 
-pub fn generate_mock_compile_request() -> CompilationRequest {
+/// Total size in bytes of the files under `path`, unreadable entries are skipped.
+pub fn directory_size<P: AsRef<Path>>(path: P) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+pub fn generate_mock_compile_request(zksolc_version: &str) -> CompilationRequest {
     CompilationRequest {
         config: CompilationConfig {
-            version: "1.4.1".to_string(),
+            version: zksolc_version.to_string(),
             user_libraries: vec![],
+            solc_version: None,
+            optimizer: OptimizerSettings::default(),
         },
         contracts: vec![CompiledFile {
             file_name: "SimpleStorage.sol".to_string(),
@@ -1,3 +1,5 @@
 pub mod cleaner;
 pub mod hardhat_config;
+pub mod hyperloglog;
+pub mod job_log;
 pub mod lib;
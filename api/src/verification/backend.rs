@@ -0,0 +1,197 @@
+use reqwest::Client;
+use rocket::tokio;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+use crate::compiler_registry::{compiler_registry, CompilerKind};
+use crate::errors::{ApiError, VerificationError};
+use crate::handlers::types::VerifyResponse;
+use crate::networks::NetworkConfig;
+use crate::verification::etherscan::EtherscanClient;
+use crate::verification::outcome::VerificationOutcome;
+use crate::verification::sourcify::SourcifyClient;
+use crate::verification::zksync_explorer::ZksyncExplorerClient;
+use crate::verification::{VerificationConfig, VerificationPayload};
+
+/// solc builds, listing the long version of each release.
+pub const SOLC_LIST_URL: &str = "https://binaries.soliditylang.org/linux-amd64/list.json";
+
+/// Time the solc list is trusted for before a version missing from it is fetched again.
+const SOLC_LIST_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Time the solc list has to be fetched in.
+const SOLC_LIST_TIMEOUT: Duration = Duration::from_secs(10);
+
+static SOLC_LONG_VERSIONS: OnceLock<RwLock<Option<SolcLongVersions>>> = OnceLock::new();
+
+/// Long version of every solc release, keyed by version, as of `fetched_at`.
+#[derive(Debug)]
+struct SolcLongVersions {
+    fetched_at: Instant,
+    long_versions: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct SolcList {
+    builds: Vec<SolcListBuild>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+struct SolcListBuild {
+    version: String,
+    long_version: String,
+}
+
+/// The `0.8.24+commit.e11b9ed9` form of solc `version` verifiers expect. Taken from the
+/// installed compiler when it knows its commit, from the solc list at `list_url` otherwise.
+pub async fn solc_long_version(
+    client: &Client,
+    list_url: &str,
+    version: &str,
+) -> Result<String, VerificationError> {
+    let version = version.trim_start_matches('v');
+    if version.contains("+commit.") {
+        return Ok(version.to_string());
+    }
+    if let Some(solc) = compiler_registry()
+        .get(CompilerKind::Solc, version)
+        .filter(|solc| solc.commit.is_some())
+    {
+        return Ok(solc.long_version());
+    }
+
+    let cache = SOLC_LONG_VERSIONS.get_or_init(Default::default);
+    if let Some(cached) = cache.read().unwrap().as_ref() {
+        match cached.long_versions.get(version) {
+            Some(long_version) => return Ok(long_version.clone()),
+            // the release may be newer than the list
+            None if cached.fetched_at.elapsed() < SOLC_LIST_TTL => {
+                return Err(VerificationError::UnknownSolcVersion(version.to_string()))
+            }
+            None => {}
+        }
+    }
+
+    let list: SolcList = client
+        .get(list_url)
+        .timeout(SOLC_LIST_TIMEOUT)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let long_versions: HashMap<String, String> = list
+        .builds
+        .into_iter()
+        .map(|build| (build.version, build.long_version))
+        .collect();
+    let long_version = long_versions.get(version).cloned();
+
+    *cache.write().unwrap() = Some(SolcLongVersions {
+        fetched_at: Instant::now(),
+        long_versions,
+    });
+    long_version.ok_or_else(|| VerificationError::UnknownSolcVersion(version.to_string()))
+}
+
+/// Verifier a request submits to, each one configured per network.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, rocket::FromFormField,
+)]
+#[serde(crate = "rocket::serde", rename_all = "snake_case")]
+pub enum VerifierKind {
+    /// The zkSync block explorer, at the network's `verifier_url`.
+    #[default]
+    #[field(value = "zksync_explorer")]
+    ZksyncExplorer,
+    /// An Etherscan-compatible API such as Era scan, at the network's `etherscan_url`.
+    #[field(value = "etherscan")]
+    Etherscan,
+    /// A Sourcify-style server, at the network's `sourcify_url`.
+    #[field(value = "sourcify")]
+    Sourcify,
+}
+
+impl VerifierKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            VerifierKind::ZksyncExplorer => "zksync_explorer",
+            VerifierKind::Etherscan => "etherscan",
+            VerifierKind::Sourcify => "sourcify",
+        }
+    }
+}
+
+/// A verifier contracts are submitted to straight from the API, without `hardhat verify`.
+#[rocket::async_trait]
+pub trait VerificationBackend: Send + Sync {
+    /// Submits `payload`, returning the id to follow the verification with.
+    async fn submit_verification(
+        &self,
+        payload: &VerificationPayload,
+    ) -> Result<String, VerificationError>;
+
+    /// Current outcome of the verification `verification_id`.
+    async fn verification_status(
+        &self,
+        verification_id: &str,
+    ) -> Result<VerifyResponse, VerificationError>;
+
+    /// Submits `payload` and polls its status until the verifier is done with it.
+    async fn verify_contract(
+        &self,
+        payload: &VerificationPayload,
+        config: &VerificationConfig,
+    ) -> Result<VerifyResponse, VerificationError> {
+        let id = self.submit_verification(payload).await?;
+        info!(
+            "Verification of {} submitted with id {}",
+            payload.contract_address, id
+        );
+
+        let start = Instant::now();
+        loop {
+            let response = self.verification_status(&id).await?;
+            debug!("Verification {} is {:?}", id, response.outcome);
+            if !matches!(response.outcome, VerificationOutcome::Pending { .. }) {
+                return Ok(response);
+            }
+            if start.elapsed() >= Duration::from_secs(config.timeout) {
+                return Err(VerificationError::Timeout(id));
+            }
+
+            tokio::time::sleep(Duration::from_millis(config.poll_interval)).await;
+        }
+    }
+}
+
+/// The `kind` verifier of `network`, if the network has one.
+pub fn verification_backend(
+    kind: VerifierKind,
+    network: &NetworkConfig,
+) -> Result<Box<dyn VerificationBackend>, ApiError> {
+    let no_verifier = || ApiError::NoVerifier(network.name.clone(), kind.label());
+
+    Ok(match kind {
+        VerifierKind::ZksyncExplorer => {
+            let url = network.verifier_url.as_deref().ok_or_else(no_verifier)?;
+            Box::new(ZksyncExplorerClient::new(url))
+        }
+        VerifierKind::Etherscan => {
+            let url = network.etherscan_url.as_deref().ok_or_else(no_verifier)?;
+            Box::new(EtherscanClient::new(
+                url,
+                network.etherscan_api_key.as_deref(),
+            ))
+        }
+        VerifierKind::Sourcify => {
+            let url = network.sourcify_url.as_deref().ok_or_else(no_verifier)?;
+            Box::new(SourcifyClient::new(url, network.chain_id))
+        }
+    })
+}
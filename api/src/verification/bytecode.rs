@@ -0,0 +1,138 @@
+use rocket::serde::json::{serde_json, Value};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::errors::VerificationError;
+use crate::handlers::types::CompiledFile;
+
+/// zksolc appends the keccak256 metadata hash to the bytecode, padded up to an odd number
+/// of words, so it lives in the last two words.
+const METADATA_TAIL_LEN: usize = 64;
+
+/// Outcome of comparing a local compilation against the deployed contract.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct BytecodeComparison {
+    pub matches: bool,
+    /// EraVM bytecode hashes, as stored by the `AccountCodeStorage`.
+    pub local_hash: String,
+    pub deployed_hash: Option<String>,
+    pub local_length: usize,
+    pub deployed_length: usize,
+    /// Settings the sources were compiled with locally.
+    pub local_settings: Value,
+    /// What differs, and which settings or metadata likely cause it.
+    pub differences: Vec<String>,
+}
+
+/// Versioned EraVM hash of `bytecode`: its SHA-256 with the first two bytes replaced by the
+/// version and the next two by the length in words.
+pub fn eravm_bytecode_hash(bytecode: &[u8]) -> [u8; 32] {
+    let mut hash: [u8; 32] = Sha256::digest(bytecode).into();
+    let words = (bytecode.len() / 32) as u16;
+    hash[0] = 1;
+    hash[1] = 0;
+    hash[2..4].copy_from_slice(&words.to_be_bytes());
+    hash
+}
+
+pub fn compare_bytecode(
+    local: &[u8],
+    deployed: &[u8],
+    local_settings: Value,
+) -> BytecodeComparison {
+    let local_hash = eravm_bytecode_hash(local);
+    let deployed_hash = (!deployed.is_empty()).then(|| eravm_bytecode_hash(deployed));
+    let matches = Some(local_hash) == deployed_hash;
+
+    let mut differences = vec![];
+    if deployed.is_empty() {
+        differences.push("No contract is deployed at the address".to_string());
+    } else if local.len() != deployed.len() {
+        differences.push(format!(
+            "Bytecode size differs ({} bytes locally, {} deployed): the zksolc or solc \
+             version, the optimizer settings, the libraries or the sources differ",
+            local.len(),
+            deployed.len()
+        ));
+    } else if !matches {
+        let first_difference = local
+            .iter()
+            .zip(deployed)
+            .position(|(local, deployed)| local != deployed)
+            .unwrap_or_default();
+
+        if first_difference >= local.len().saturating_sub(METADATA_TAIL_LEN) {
+            differences.push(
+                "Only the metadata hash differs: the sources differ in comments or formatting, \
+                 or the metadata settings differ"
+                    .to_string(),
+            );
+        } else {
+            differences.push(format!(
+                "Instructions differ from byte {}: the compiler versions, the optimizer \
+                 settings, the libraries or the sources differ",
+                first_difference
+            ));
+        }
+    }
+
+    BytecodeComparison {
+        matches,
+        local_hash: format!("0x{}", hex::encode(local_hash)),
+        deployed_hash: deployed_hash.map(|hash| format!("0x{}", hex::encode(hash))),
+        local_length: local.len(),
+        deployed_length: deployed.len(),
+        local_settings,
+        differences,
+    }
+}
+
+/// Deployed bytecode of `target_contract` (`path/Some.sol:ContractName`) in the compile
+/// artifacts.
+pub fn artifact_bytecode(
+    artifacts: &[CompiledFile],
+    target_contract: &str,
+) -> Result<Vec<u8>, VerificationError> {
+    let (source, name) = target_contract
+        .rsplit_once(':')
+        .ok_or(VerificationError::MissingTargetContract)?;
+    let artifact_name = format!("{}/{}.json", source, name);
+
+    let artifact = artifacts
+        .iter()
+        .find(|file| file.file_name == artifact_name)
+        .ok_or_else(|| VerificationError::ArtifactNotFound(target_contract.to_string()))?;
+    let artifact: Value = serde_json::from_str(&artifact.file_content)
+        .map_err(|_| VerificationError::ArtifactNotFound(target_contract.to_string()))?;
+
+    let bytecode = artifact["deployedBytecode"]
+        .as_str()
+        .ok_or_else(|| VerificationError::ArtifactNotFound(target_contract.to_string()))?;
+    hex::decode(bytecode.trim_start_matches("0x"))
+        .map_err(|_| VerificationError::ArtifactNotFound(target_contract.to_string()))
+}
+
+#[test]
+fn test_compare_bytecode() {
+    let local = vec![0xaa; 32 * 5];
+    let hash = eravm_bytecode_hash(&local);
+    assert_eq!(&hash[..4], &[1, 0, 0, 5]);
+
+    assert!(compare_bytecode(&local, &local, Value::Null).matches);
+
+    let mut metadata_changed = local.clone();
+    *metadata_changed.last_mut().unwrap() = 0xbb;
+    let comparison = compare_bytecode(&local, &metadata_changed, Value::Null);
+    assert!(!comparison.matches);
+    assert!(comparison.differences[0].starts_with("Only the metadata hash differs"));
+
+    let mut code_changed = local.clone();
+    code_changed[3] = 0xbb;
+    let comparison = compare_bytecode(&local, &code_changed, Value::Null);
+    assert!(comparison.differences[0].starts_with("Instructions differ from byte 3"));
+
+    let comparison = compare_bytecode(&local, &[], Value::Null);
+    assert!(!comparison.matches);
+    assert_eq!(comparison.deployed_hash, None);
+}
@@ -0,0 +1,232 @@
+use ethabi::ethereum_types::U256;
+use ethabi::token::{LenientTokenizer, Tokenizer};
+use ethabi::{Contract, ParamType, Token};
+use rocket::serde::json::{serde_json, Value};
+
+use crate::errors::VerificationError;
+use crate::handlers::types::VerificationRequest;
+
+/// Constructor arguments of a verification request, checked against the constructor ABI
+/// whenever the request carries one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstructorArgs {
+    /// ABI encoded, `0x` prefixed.
+    pub encoded: Option<String>,
+    /// The arguments as JSON values, the way hardhat takes them.
+    pub values: Option<Vec<Value>>,
+}
+
+impl ConstructorArgs {
+    /// Resolves the arguments from either the pre-encoded `constructor_args` or the typed
+    /// `inputs`, encoding or decoding them against the request's ABI.
+    pub fn from_request(request: &VerificationRequest) -> Result<Self, VerificationError> {
+        let params = request.abi.as_ref().map(constructor_params).transpose()?;
+
+        if let Some(ref encoded) = request.config.constructor_args {
+            let encoded = encoded.trim_start_matches("0x");
+            let data = hex::decode(encoded).map_err(|e| {
+                VerificationError::InvalidConstructorArgs(format!("invalid hex: {}", e))
+            })?;
+
+            let values = match params {
+                Some(ref params) => {
+                    let tokens = ethabi::decode(params, &data)
+                        .map_err(|e| VerificationError::InvalidConstructorArgs(e.to_string()))?;
+                    Some(tokens.iter().map(token_to_json).collect())
+                }
+                None if data.is_empty() => Some(vec![]),
+                None => None,
+            };
+
+            return Ok(Self {
+                encoded: Some(format!("0x{}", encoded)),
+                values,
+            });
+        }
+
+        let Some(params) = params else {
+            // Nothing to check the inputs against, hardhat gets them as they are
+            return Ok(Self {
+                encoded: request.config.inputs.is_empty().then(|| "0x".to_string()),
+                values: Some(request.config.inputs.clone()),
+            });
+        };
+
+        let tokens = tokenize_args(&params, &request.config.inputs)?;
+        Ok(Self {
+            encoded: Some(format!("0x{}", hex::encode(ethabi::encode(&tokens)))),
+            values: Some(tokens.iter().map(token_to_json).collect()),
+        })
+    }
+}
+
+fn constructor_params(abi: &Value) -> Result<Vec<ParamType>, VerificationError> {
+    let contract: Contract = serde_json::from_value(abi.clone())
+        .map_err(|e| VerificationError::InvalidConstructorArgs(format!("invalid ABI: {}", e)))?;
+
+    Ok(contract
+        .constructor
+        .map(|constructor| {
+            constructor
+                .inputs
+                .into_iter()
+                .map(|param| param.kind)
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+fn tokenize_args(params: &[ParamType], values: &[Value]) -> Result<Vec<Token>, VerificationError> {
+    if params.len() != values.len() {
+        return Err(VerificationError::InvalidConstructorArgs(format!(
+            "expected {} arguments, got {}",
+            params.len(),
+            values.len()
+        )));
+    }
+
+    params
+        .iter()
+        .zip(values)
+        .enumerate()
+        .map(|(i, (param, value))| {
+            tokenize(param, value).map_err(|e| {
+                VerificationError::InvalidConstructorArgs(format!("argument {}: {}", i, e))
+            })
+        })
+        .collect()
+}
+
+/// Converts `value` to a token of type `param`. Scalars may be given as strings, which is
+/// the only lossless way for large integers.
+fn tokenize(param: &ParamType, value: &Value) -> Result<Token, String> {
+    let tokenize_str =
+        |value: &str| LenientTokenizer::tokenize(param, value).map_err(|e| e.to_string());
+
+    match (param, value) {
+        (ParamType::Array(inner), Value::Array(values)) => values
+            .iter()
+            .map(|value| tokenize(inner, value))
+            .collect::<Result<_, _>>()
+            .map(Token::Array),
+        (ParamType::FixedArray(inner, len), Value::Array(values)) => {
+            if values.len() != *len {
+                return Err(format!("expected {} elements, got {}", len, values.len()));
+            }
+            values
+                .iter()
+                .map(|value| tokenize(inner, value))
+                .collect::<Result<_, _>>()
+                .map(Token::FixedArray)
+        }
+        (ParamType::Tuple(params), Value::Array(values)) => {
+            if values.len() != params.len() {
+                return Err(format!(
+                    "expected {} tuple fields, got {}",
+                    params.len(),
+                    values.len()
+                ));
+            }
+            params
+                .iter()
+                .zip(values)
+                .map(|(param, value)| tokenize(param, value))
+                .collect::<Result<_, _>>()
+                .map(Token::Tuple)
+        }
+        (
+            ParamType::Array(_) | ParamType::FixedArray(..) | ParamType::Tuple(_),
+            Value::String(s),
+        ) => {
+            let value: Value = serde_json::from_str(s).map_err(|e| e.to_string())?;
+            tokenize(param, &value)
+        }
+        (_, Value::String(s)) => tokenize_str(s),
+        (ParamType::Int(_) | ParamType::Uint(_), Value::Number(n)) => tokenize_str(&n.to_string()),
+        (ParamType::Bool, Value::Bool(b)) => Ok(Token::Bool(*b)),
+        _ => Err(format!("{} is not a valid {}", value, param)),
+    }
+}
+
+fn token_to_json(token: &Token) -> Value {
+    match token {
+        Token::Address(address) => Value::String(format!("{:#x}", address)),
+        Token::Bytes(bytes) | Token::FixedBytes(bytes) => {
+            Value::String(format!("0x{}", hex::encode(bytes)))
+        }
+        Token::Uint(n) => Value::String(n.to_string()),
+        Token::Int(n) if n.bit(255) => {
+            // Two's complement
+            Value::String(format!("-{}", (!*n).overflowing_add(U256::one()).0))
+        }
+        Token::Int(n) => Value::String(n.to_string()),
+        Token::Bool(b) => Value::Bool(*b),
+        Token::String(s) => Value::String(s.clone()),
+        Token::Array(tokens) | Token::FixedArray(tokens) | Token::Tuple(tokens) => {
+            Value::Array(tokens.iter().map(token_to_json).collect())
+        }
+    }
+}
+
+#[test]
+fn test_constructor_args_encoding() {
+    use crate::handlers::types::VerifyConfig;
+    use crate::verification::OptimizerSettings;
+    use serde_json::json;
+
+    let abi = json!([{
+        "type": "constructor",
+        "stateMutability": "nonpayable",
+        "inputs": [
+            { "name": "owner", "type": "address" },
+            { "name": "amounts", "type": "uint256[]" },
+            { "name": "delta", "type": "int8" },
+            { "name": "name", "type": "string" },
+        ],
+    }]);
+    let mut request = VerificationRequest {
+        config: VerifyConfig {
+            zksolc_version: "1.5.6".to_string(),
+            solc_version: None,
+            network: "sepolia".to_string(),
+            contract_address: "0x0000000000000000000000000000000000000001".to_string(),
+            inputs: vec![
+                json!("0x00000000000000000000000000000000000000aa"),
+                json!(["1", 2, "115792089237316195423570985008687907853269984665640564039457584007913129639935"]),
+                json!(-1),
+                json!("hello world"),
+            ],
+            constructor_args: None,
+            check_bytecode: None,
+            deployed_bytecode: None,
+            optimizer: OptimizerSettings::default(),
+            user_libraries: vec![],
+            verifier: None,
+        },
+        contracts: vec![],
+        target_contract: None,
+        abi: Some(abi),
+        compilation_id: None,
+        target_path: None,
+        proxy: None,
+    };
+
+    let args = ConstructorArgs::from_request(&request).unwrap();
+    let encoded = args.encoded.clone().unwrap();
+    assert_eq!(args.values.as_ref().unwrap()[2], json!("-1"));
+    assert_eq!(args.values.as_ref().unwrap()[3], json!("hello world"));
+
+    // Pre-encoded arguments decode to the same values
+    request.config.constructor_args = Some(encoded.clone());
+    assert_eq!(ConstructorArgs::from_request(&request).unwrap(), args);
+
+    request.config.constructor_args = None;
+    request.config.inputs[2] = json!("not a number");
+    assert!(matches!(
+        ConstructorArgs::from_request(&request),
+        Err(VerificationError::InvalidConstructorArgs(_))
+    ));
+
+    request.config.inputs.pop();
+    assert!(ConstructorArgs::from_request(&request).is_err());
+}
@@ -0,0 +1,226 @@
+use reqwest::Client;
+use rocket::serde::json::Value;
+use serde::Deserialize;
+
+use crate::errors::VerificationError;
+use crate::handlers::types::VerifyResponse;
+use crate::verification::backend::{solc_long_version, VerificationBackend, SOLC_LIST_URL};
+use crate::verification::outcome::VerificationOutcome;
+use crate::verification::{http_client, VerificationPayload};
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct EtherscanResponse {
+    /// `"1"` on success, `"0"` otherwise.
+    status: String,
+    /// The verification GUID, its status or the error.
+    result: Value,
+}
+
+impl EtherscanResponse {
+    fn result_text(&self) -> String {
+        match self.result {
+            Value::String(ref result) => result.clone(),
+            ref result => result.to_string(),
+        }
+    }
+}
+
+/// Client of an Etherscan-compatible verification API, such as Era scan's: sources are
+/// submitted with `action=verifysourcecode`, which answers with a GUID whose status is
+/// polled with `action=checkverifystatus`.
+#[derive(Debug, Clone)]
+pub struct EtherscanClient {
+    client: Client,
+    api_url: String,
+    api_key: Option<String>,
+    solc_list_url: String,
+}
+
+impl EtherscanClient {
+    pub fn new(api_url: &str, api_key: Option<&str>) -> Self {
+        Self {
+            client: http_client().clone(),
+            api_url: api_url.to_string(),
+            api_key: api_key.map(String::from),
+            solc_list_url: SOLC_LIST_URL.to_string(),
+        }
+    }
+
+    async fn parse_response(
+        response: reqwest::Response,
+    ) -> Result<EtherscanResponse, VerificationError> {
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(VerificationError::Rejected(status.as_u16(), body));
+        }
+
+        rocket::serde::json::from_str(&body).map_err(|_| VerificationError::InvalidResponse(body))
+    }
+}
+
+#[rocket::async_trait]
+impl VerificationBackend for EtherscanClient {
+    async fn submit_verification(
+        &self,
+        payload: &VerificationPayload,
+    ) -> Result<String, VerificationError> {
+        let solc_version = solc_long_version(
+            &self.client,
+            &self.solc_list_url,
+            &payload.compiler_solc_version,
+        )
+        .await?;
+        let mut form = vec![
+            ("module", "contract".to_string()),
+            ("action", "verifysourcecode".to_string()),
+            ("contractaddress", payload.contract_address.clone()),
+            ("sourceCode", payload.source_code.to_string()),
+            ("codeformat", payload.code_format.clone()),
+            ("contractname", payload.contract_name.clone()),
+            ("compilerversion", format!("v{}", solc_version)),
+            ("zksolcVersion", payload.compiler_zksolc_version.clone()),
+            ("compilermode", "zksync".to_string()),
+            (
+                "optimizationUsed",
+                if payload.optimization_used { "1" } else { "0" }.to_string(),
+            ),
+            // Misspelled by the API itself
+            (
+                "constructorArguements",
+                payload
+                    .constructor_arguments
+                    .trim_start_matches("0x")
+                    .to_string(),
+            ),
+        ];
+        if let Some(ref api_key) = self.api_key {
+            form.push(("apikey", api_key.clone()));
+        }
+
+        let response = self.client.post(&self.api_url).form(&form).send().await?;
+        let response = Self::parse_response(response).await?;
+        if response.status != "1" {
+            // Etherscan reports its errors with a 200
+            return Err(VerificationError::Rejected(200, response.result_text()));
+        }
+
+        Ok(response.result_text())
+    }
+
+    async fn verification_status(
+        &self,
+        verification_id: &str,
+    ) -> Result<VerifyResponse, VerificationError> {
+        let mut query = vec![
+            ("module", "contract"),
+            ("action", "checkverifystatus"),
+            ("guid", verification_id),
+        ];
+        if let Some(ref api_key) = self.api_key {
+            query.push(("apikey", api_key));
+        }
+
+        let response = self.client.get(&self.api_url).query(&query).send().await?;
+        let response = Self::parse_response(response).await?;
+        let result = response.result_text();
+
+        let lowercase = result.to_ascii_lowercase();
+        let outcome = if lowercase.starts_with("pending") || lowercase.contains("in progress") {
+            VerificationOutcome::Pending {
+                verification_id: verification_id.to_string(),
+            }
+        } else if lowercase.starts_with("pass") {
+            VerificationOutcome::Verified
+        } else {
+            VerificationOutcome::from_message(&result)
+        };
+
+        Ok(VerifyResponse::new(outcome, result))
+    }
+}
+
+#[rocket::async_test]
+async fn test_etherscan_verification() {
+    use crate::verification::VerificationConfig;
+    use mockito::Matcher;
+
+    crate::config::init_test_app_config();
+
+    let mut server = mockito::Server::new_async().await;
+    let solc_list = server
+        .mock("GET", "/list.json")
+        .with_body(r#"{"builds":[{"version":"0.8.24","longVersion":"0.8.24+commit.e11b9ed9"}]}"#)
+        .expect(1)
+        .create_async()
+        .await;
+    let submit = server
+        .mock("POST", "/api")
+        .match_body(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("action".into(), "verifysourcecode".into()),
+            Matcher::UrlEncoded(
+                "contractname".into(),
+                "contracts/Greeter.sol:Greeter".into(),
+            ),
+            Matcher::UrlEncoded("compilerversion".into(), "v0.8.24+commit.e11b9ed9".into()),
+            Matcher::UrlEncoded("zksolcVersion".into(), "v1.5.6".into()),
+            Matcher::UrlEncoded("constructorArguements".into(), "".into()),
+            Matcher::UrlEncoded("apikey".into(), "key".into()),
+        ]))
+        .with_body(r#"{"status":"1","message":"OK","result":"guid-1"}"#)
+        .create_async()
+        .await;
+    // Pending on the first poll, verified from then on
+    let polls = std::sync::atomic::AtomicUsize::new(0);
+    let status = server
+        .mock("GET", "/api")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("action".into(), "checkverifystatus".into()),
+            Matcher::UrlEncoded("guid".into(), "guid-1".into()),
+        ]))
+        .with_body_from_request(move |_| {
+            match polls.fetch_add(1, std::sync::atomic::Ordering::SeqCst) {
+                0 => br#"{"status":"0","message":"NOTOK","result":"Pending in queue"}"#.to_vec(),
+                _ => br#"{"status":"1","message":"OK","result":"Pass - Verified"}"#.to_vec(),
+            }
+        })
+        .expect(2)
+        .create_async()
+        .await;
+
+    let payload = VerificationPayload {
+        contract_address: "0x0000000000000000000000000000000000000001".to_string(),
+        source_code: rocket::serde::json::json!({ "language": "Solidity" }),
+        code_format: "solidity-standard-json-input".to_string(),
+        contract_name: "contracts/Greeter.sol:Greeter".to_string(),
+        compiler_zksolc_version: "v1.5.6".to_string(),
+        compiler_solc_version: "0.8.24".to_string(),
+        optimization_used: true,
+        constructor_arguments: "0x".to_string(),
+    };
+    let config = VerificationConfig {
+        poll_interval: 10,
+        ..VerificationConfig::default()
+    };
+
+    let mut client = EtherscanClient::new(&format!("{}/api", server.url()), Some("key"));
+    client.solc_list_url = format!("{}/list.json", server.url());
+    let response = client.verify_contract(&payload, &config).await.unwrap();
+
+    // The list is fetched once, then kept along with the releases it doesn't know about
+    assert_eq!(
+        solc_long_version(&client.client, &client.solc_list_url, "0.8.24")
+            .await
+            .unwrap(),
+        "0.8.24+commit.e11b9ed9"
+    );
+    assert!(matches!(
+        solc_long_version(&client.client, &client.solc_list_url, "0.8.99").await,
+        Err(VerificationError::UnknownSolcVersion(_))
+    ));
+    solc_list.assert_async().await;
+    submit.assert_async().await;
+    status.assert_async().await;
+    assert_eq!(response.outcome, VerificationOutcome::Verified);
+}
@@ -0,0 +1,192 @@
+pub mod backend;
+pub mod bytecode;
+pub mod constructor_args;
+pub mod etherscan;
+pub mod outcome;
+pub mod proxy;
+pub mod rpc;
+pub mod sourcify;
+pub mod zksync_explorer;
+
+use reqwest::Client;
+use rocket::serde::json::serde_json;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::config::app_config;
+use crate::errors::VerificationError;
+use crate::handlers::types::{Libraries, VerificationRequest};
+use crate::verification::constructor_args::ConstructorArgs;
+
+const STANDARD_JSON_CODE_FORMAT: &str = "solidity-standard-json-input";
+
+static HTTP_CLIENT: OnceLock<Client> = OnceLock::new();
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde", default)]
+pub struct VerificationConfig {
+    /// Submit verifications straight to the network's verifier instead of running
+    /// `hardhat verify`.
+    pub native_client: bool,
+    /// Milliseconds between two verification status requests.
+    pub poll_interval: u64,
+    /// Seconds to wait for the verifier to reach a final status.
+    pub timeout: u64,
+    /// Compile the sources and compare them with the deployed bytecode before submitting,
+    /// unless the request says otherwise.
+    pub check_bytecode: bool,
+    /// Seconds the sources of a compilation are kept for verifying it by its id.
+    pub compilation_ttl: u64,
+    /// Compilations kept at most, the oldest ones are dropped past this.
+    pub max_stored_compilations: usize,
+    /// Bytes of sources a compilation may have at most to be kept, larger ones get no
+    /// compilation id.
+    pub max_stored_compilation_size: usize,
+    /// Contracts a batch verification request may list at most.
+    pub max_batch_size: usize,
+    /// Seconds to wait for a connection to a verifier or RPC node.
+    pub connect_timeout: u64,
+    /// Seconds a single request to a verifier or RPC node may take.
+    pub request_timeout: u64,
+}
+
+impl Default for VerificationConfig {
+    fn default() -> Self {
+        Self {
+            native_client: false,
+            poll_interval: 2_000,
+            timeout: 120,
+            check_bytecode: false,
+            compilation_ttl: 60 * 60,
+            max_stored_compilations: 200,
+            max_stored_compilation_size: 512 * 1024,
+            max_batch_size: 20,
+            connect_timeout: 10,
+            request_timeout: 30,
+        }
+    }
+}
+
+impl VerificationConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_stored_compilations == 0 {
+            return Err("verification.max_stored_compilations must be at least 1".to_string());
+        }
+        if self.max_batch_size == 0 {
+            return Err("verification.max_batch_size must be at least 1".to_string());
+        }
+        if self.connect_timeout == 0 || self.request_timeout == 0 {
+            return Err(
+                "verification.connect_timeout and request_timeout must be at least 1".to_string(),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// HTTP client shared by the verifier and RPC clients, with the timeouts of the
+/// `verification` configuration.
+pub fn http_client() -> &'static Client {
+    HTTP_CLIENT.get_or_init(|| {
+        let config = &app_config().verification;
+        Client::builder()
+            .connect_timeout(Duration::from_secs(config.connect_timeout))
+            .timeout(Duration::from_secs(config.request_timeout))
+            .build()
+            .expect("failed to build the HTTP client")
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct OptimizerSettings {
+    pub enabled: bool,
+    pub mode: String,
+}
+
+impl Default for OptimizerSettings {
+    /// zksolc's own defaults, which the hardhat config doesn't override.
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            mode: "3".to_string(),
+        }
+    }
+}
+
+/// Body of a contract verification request, in the format of the zkSync explorer's
+/// `contract_verification` endpoint.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct VerificationPayload {
+    pub contract_address: String,
+    /// Solidity standard JSON input with every source of the request.
+    pub source_code: serde_json::Value,
+    pub code_format: String,
+    /// Fully qualified, e.g. `contracts/Greeter.sol:Greeter`.
+    pub contract_name: String,
+    pub compiler_zksolc_version: String,
+    pub compiler_solc_version: String,
+    pub optimization_used: bool,
+    /// ABI encoded, `0x` prefixed.
+    pub constructor_arguments: String,
+}
+
+impl VerificationPayload {
+    pub fn new(
+        request: &VerificationRequest,
+        solc_version: &str,
+        libraries: &Libraries,
+        constructor_args: &ConstructorArgs,
+    ) -> Result<Self, VerificationError> {
+        let optimizer = &request.config.optimizer;
+        let contract_name = request
+            .target_contract
+            .clone()
+            .ok_or(VerificationError::MissingTargetContract)?;
+
+        let constructor_arguments = constructor_args
+            .encoded
+            .clone()
+            .ok_or(VerificationError::ConstructorAbiRequired)?;
+
+        Ok(Self {
+            contract_address: request.config.contract_address.clone(),
+            source_code: standard_json_input(request, libraries),
+            code_format: STANDARD_JSON_CODE_FORMAT.to_string(),
+            contract_name,
+            compiler_zksolc_version: format!("v{}", request.config.zksolc_version),
+            compiler_solc_version: solc_version.to_string(),
+            optimization_used: optimizer.enabled,
+            constructor_arguments,
+        })
+    }
+}
+
+/// The Solidity sources of `request` as standard JSON input, keyed by their file names the
+/// way hardhat sees them.
+fn standard_json_input(request: &VerificationRequest, libraries: &Libraries) -> serde_json::Value {
+    let sources: serde_json::Map<String, serde_json::Value> = request
+        .contracts
+        .iter()
+        .filter(|file| file.file_name.ends_with(".sol"))
+        .map(|file| {
+            (
+                file.file_name.clone(),
+                serde_json::json!({ "content": file.file_content }),
+            )
+        })
+        .collect();
+
+    serde_json::json!({
+        "language": "Solidity",
+        "sources": sources,
+        "settings": {
+            "optimizer": request.config.optimizer,
+            "libraries": libraries,
+            "outputSelection": { "*": { "*": ["abi"] } },
+        },
+    })
+}
@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{ApiError, VerificationError};
+use crate::verification::zksync_explorer::{VerificationState, VerificationStatus};
+
+/// Phrase the explorer and hardhat print along with a verification id.
+const VERIFICATION_ID_MARKER: &str = "verification ID is:";
+
+/// How a verification ended, as far as the verifier is concerned.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde", tag = "outcome", rename_all = "snake_case")]
+pub enum VerificationOutcome {
+    Verified,
+    AlreadyVerified,
+    /// Still queued or running at the verifier, see `/verify-status/<verification_id>`.
+    Pending {
+        /// Numeric at the zkSync explorer, a GUID at Etherscan-compatible verifiers.
+        verification_id: String,
+    },
+    /// The sources or settings don't produce the deployed bytecode.
+    BytecodeMismatch,
+    /// Nothing is deployed at the contract address.
+    ContractNotFound,
+    /// The verifier couldn't be reached or failed on its end.
+    VerifierUnavailable,
+    /// Any other failure, e.g. invalid sources or arguments.
+    Failed,
+}
+
+impl VerificationOutcome {
+    /// The legacy `status` of the response.
+    pub fn status(&self) -> &'static str {
+        match self {
+            VerificationOutcome::Verified | VerificationOutcome::AlreadyVerified => "Success",
+            VerificationOutcome::Pending { .. } => "Pending",
+            _ => "Error",
+        }
+    }
+
+    /// Recognizes the outcome from a verifier or hardhat error message.
+    pub fn from_message(message: &str) -> Self {
+        let message = message.to_ascii_lowercase();
+        if message.contains("already verified") {
+            VerificationOutcome::AlreadyVerified
+        } else if message.contains("bytecode mismatch")
+            || message.contains("bytecode does not match")
+            || message.contains("deployed bytecode doesn't match")
+        {
+            VerificationOutcome::BytecodeMismatch
+        } else if message.contains("no bytecode")
+            || message.contains("not deployed")
+            || message.contains("contract not found")
+            || message.contains("no deployed contract")
+        {
+            VerificationOutcome::ContractNotFound
+        } else if message.contains("econnrefused")
+            || message.contains("etimedout")
+            || message.contains("service unavailable")
+            || message.contains("bad gateway")
+        {
+            VerificationOutcome::VerifierUnavailable
+        } else {
+            VerificationOutcome::Failed
+        }
+    }
+
+    /// Classifies the output of a successful `hardhat verify` run.
+    pub fn from_hardhat_output(stdout: &str) -> Self {
+        let lowercase = stdout.to_ascii_lowercase();
+        if lowercase.contains("already verified") {
+            return VerificationOutcome::AlreadyVerified;
+        }
+        if lowercase.contains("successfully verified") {
+            return VerificationOutcome::Verified;
+        }
+
+        match verification_id(stdout) {
+            Some(verification_id) => VerificationOutcome::Pending { verification_id },
+            None => VerificationOutcome::Verified,
+        }
+    }
+
+    pub fn from_status(id: u64, status: &VerificationStatus) -> Self {
+        match status.status {
+            VerificationState::Successful => VerificationOutcome::Verified,
+            VerificationState::Queued | VerificationState::InProgress => {
+                VerificationOutcome::Pending {
+                    verification_id: id.to_string(),
+                }
+            }
+            VerificationState::Failed => {
+                VerificationOutcome::from_message(status.error.as_deref().unwrap_or_default())
+            }
+        }
+    }
+
+    pub fn from_error(error: &ApiError) -> Self {
+        match error {
+            ApiError::VerificationError(error) => match error {
+                VerificationError::Request(_) => VerificationOutcome::VerifierUnavailable,
+                VerificationError::Rejected(status, _) if *status >= 500 => {
+                    VerificationOutcome::VerifierUnavailable
+                }
+                VerificationError::Rejected(_, message) => {
+                    VerificationOutcome::from_message(message)
+                }
+                VerificationError::Timeout(id) => VerificationOutcome::Pending {
+                    verification_id: id.clone(),
+                },
+                _ => VerificationOutcome::Failed,
+            },
+            _ => VerificationOutcome::Failed,
+        }
+    }
+}
+
+/// The verification id printed in `output`, if any.
+pub fn verification_id(output: &str) -> Option<String> {
+    let lowercase = output.to_ascii_lowercase();
+    let start = lowercase.find(&VERIFICATION_ID_MARKER.to_ascii_lowercase())?
+        + VERIFICATION_ID_MARKER.len();
+
+    output[start..]
+        .trim_start()
+        .split(|c: char| !c.is_ascii_digit())
+        .next()
+        .filter(|id| !id.is_empty())
+        .map(String::from)
+}
+
+#[test]
+fn test_verification_outcomes() {
+    assert_eq!(
+        VerificationOutcome::from_hardhat_output(
+            "Your verification ID is: 12345\nContract successfully verified on zkSync block explorer!"
+        ),
+        VerificationOutcome::Verified
+    );
+    assert_eq!(
+        VerificationOutcome::from_hardhat_output("Your verification ID is: 12345\n"),
+        VerificationOutcome::Pending {
+            verification_id: "12345".to_string()
+        }
+    );
+    assert_eq!(
+        VerificationOutcome::from_message("This contract is already verified"),
+        VerificationOutcome::AlreadyVerified
+    );
+    assert_eq!(
+        VerificationOutcome::from_message(
+            "ZkSyncVerifyPluginError: The address 0x01 has no bytecode. Is the contract deployed?"
+        ),
+        VerificationOutcome::ContractNotFound
+    );
+    assert_eq!(
+        VerificationOutcome::from_error(&ApiError::VerificationError(VerificationError::Rejected(
+            503,
+            "upstream".to_string()
+        ))),
+        VerificationOutcome::VerifierUnavailable
+    );
+}
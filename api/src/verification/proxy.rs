@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+
+use crate::errors::VerificationError;
+use crate::handlers::types::VerifyResponse;
+use crate::verification::rpc::RpcClient;
+
+/// `bytes32(uint256(keccak256("eip1967.proxy.implementation")) - 1)`
+pub const IMPLEMENTATION_SLOT: &str =
+    "0x360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc";
+/// `bytes32(uint256(keccak256("eip1967.proxy.admin")) - 1)`
+pub const ADMIN_SLOT: &str = "0xb53127684a568b3173ae13b9f8a6016e243e63b6e8ee1178d6a717850b5d6103";
+/// `bytes32(uint256(keccak256("eip1967.proxy.beacon")) - 1)`
+pub const BEACON_SLOT: &str = "0xa3f0ad74e5423aebfd80d3ef4346578335a9a72aeaee59ff6cb3582b35133d50";
+/// Selector of the beacon's `implementation()`.
+const IMPLEMENTATION_SELECTOR: [u8; 4] = [0x5c, 0x60, 0xda, 0x1b];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde", rename_all = "snake_case")]
+pub enum ProxyKind {
+    /// Upgraded through its admin, which is stored in the admin slot.
+    Transparent,
+    /// Upgraded through the implementation itself, the admin slot is empty.
+    Uups,
+    /// Delegates to the implementation of the beacon stored in the beacon slot.
+    Beacon,
+}
+
+/// Implementation and admin of an EIP-1967 proxy, as read from its storage.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ProxyLinkage {
+    pub kind: ProxyKind,
+    pub proxy_address: String,
+    pub implementation_address: String,
+    pub admin_address: Option<String>,
+    pub beacon_address: Option<String>,
+}
+
+impl ProxyLinkage {
+    pub async fn read(rpc: &RpcClient, proxy_address: &str) -> Result<Self, VerificationError> {
+        let admin = slot_address(&rpc.get_storage_at(proxy_address, ADMIN_SLOT).await?);
+        let implementation = slot_address(
+            &rpc.get_storage_at(proxy_address, IMPLEMENTATION_SLOT)
+                .await?,
+        );
+        if let Some(implementation) = implementation {
+            return Ok(Self {
+                kind: if admin.is_some() {
+                    ProxyKind::Transparent
+                } else {
+                    ProxyKind::Uups
+                },
+                proxy_address: proxy_address.to_string(),
+                implementation_address: implementation,
+                admin_address: admin,
+                beacon_address: None,
+            });
+        }
+
+        let beacon = slot_address(&rpc.get_storage_at(proxy_address, BEACON_SLOT).await?)
+            .ok_or_else(|| VerificationError::NotAProxy(proxy_address.to_string()))?;
+        let returned = rpc.eth_call(&beacon, &IMPLEMENTATION_SELECTOR).await?;
+        let implementation = <[u8; 32]>::try_from(returned.as_slice())
+            .ok()
+            .and_then(|word| slot_address(&word))
+            .ok_or_else(|| {
+                VerificationError::InvalidProxy(
+                    format!("beacon {} implementation()", beacon),
+                    format!("0x{}", hex::encode(&returned)),
+                )
+            })?;
+
+        Ok(Self {
+            kind: ProxyKind::Beacon,
+            proxy_address: proxy_address.to_string(),
+            implementation_address: implementation,
+            admin_address: admin,
+            beacon_address: Some(beacon),
+        })
+    }
+}
+
+/// Outcome of a proxy verification, along with the implementation's own.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ProxyVerification {
+    #[serde(flatten)]
+    pub linkage: ProxyLinkage,
+    pub implementation: Box<VerifyResponse>,
+}
+
+/// The address held in the low 20 bytes of `word`, none if the slot is empty.
+fn slot_address(word: &[u8; 32]) -> Option<String> {
+    word.iter()
+        .any(|byte| *byte != 0)
+        .then(|| format!("0x{}", hex::encode(&word[12..])))
+}
+
+#[rocket::async_test]
+async fn test_read_proxy_linkage() {
+    use rocket::serde::json::{serde_json, Value};
+
+    crate::config::init_test_app_config();
+
+    const PROXY: &str = "0x00000000000000000000000000000000000000aa";
+    const BEACON_PROXY: &str = "0x00000000000000000000000000000000000000bb";
+    assert_eq!(
+        ethabi::short_signature("implementation", &[]),
+        IMPLEMENTATION_SELECTOR
+    );
+
+    let mut server = mockito::Server::new_async().await;
+    let storage = server
+        .mock("POST", "/")
+        .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+            "method": "eth_getStorageAt",
+        })))
+        .with_body_from_request(|request| {
+            let body: Value = serde_json::from_slice(request.body().unwrap()).unwrap();
+            let (address, slot) = (&body["params"][0], &body["params"][1]);
+            let result = if address == PROXY && slot == IMPLEMENTATION_SLOT {
+                "0x000000000000000000000000000000000000000000000000000000000000beef"
+            } else if address == BEACON_PROXY && slot == BEACON_SLOT {
+                "0x000000000000000000000000000000000000000000000000000000000000bea0"
+            } else {
+                // Stripped of its leading zeros, like some nodes do
+                "0x0"
+            };
+            serde_json::json!({ "jsonrpc": "2.0", "id": 1, "result": result })
+                .to_string()
+                .into()
+        })
+        .expect(5)
+        .create_async()
+        .await;
+    let beacon = server
+        .mock("POST", "/")
+        .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+            "method": "eth_call",
+            "params": [{ "to": "0x000000000000000000000000000000000000bea0", "data": "0x5c60da1b" }],
+        })))
+        .with_body(
+            r#"{"jsonrpc":"2.0","id":1,"result":"0x000000000000000000000000000000000000000000000000000000000000cafe"}"#,
+        )
+        .create_async()
+        .await;
+
+    let rpc = RpcClient::new(&server.url());
+    let linkage = ProxyLinkage::read(&rpc, PROXY).await.unwrap();
+    assert_eq!(linkage.kind, ProxyKind::Uups);
+    assert_eq!(
+        linkage.implementation_address,
+        "0x000000000000000000000000000000000000beef"
+    );
+    assert_eq!(linkage.admin_address, None);
+
+    let linkage = ProxyLinkage::read(&rpc, BEACON_PROXY).await.unwrap();
+    storage.assert_async().await;
+    beacon.assert_async().await;
+    assert_eq!(linkage.kind, ProxyKind::Beacon);
+    assert_eq!(
+        linkage.implementation_address,
+        "0x000000000000000000000000000000000000cafe"
+    );
+    assert_eq!(
+        linkage.beacon_address.as_deref(),
+        Some("0x000000000000000000000000000000000000bea0")
+    );
+}
@@ -0,0 +1,142 @@
+use reqwest::Client;
+use rocket::serde::json::{serde_json, Value};
+use serde::Deserialize;
+
+use crate::errors::VerificationError;
+use crate::verification::http_client;
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct RpcResponse {
+    result: Option<Value>,
+    error: Option<Value>,
+}
+
+/// Minimal JSON-RPC client for the network state verification needs.
+#[derive(Debug, Clone)]
+pub struct RpcClient {
+    client: Client,
+    url: String,
+}
+
+impl RpcClient {
+    pub fn new(url: &str) -> Self {
+        Self {
+            client: http_client().clone(),
+            url: url.to_string(),
+        }
+    }
+
+    async fn call(&self, method: &str, params: Value) -> Result<Value, VerificationError> {
+        let response: RpcResponse = self
+            .client
+            .post(&self.url)
+            .json(&serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": method,
+                "params": params,
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        if let Some(error) = response.error {
+            return Err(VerificationError::Rpc(format!("{}: {}", method, error)));
+        }
+        response
+            .result
+            .ok_or_else(|| VerificationError::Rpc(format!("{}: empty result", method)))
+    }
+
+    /// Deployed bytecode at `address`, empty when there is no contract.
+    pub async fn get_code(&self, address: &str) -> Result<Vec<u8>, VerificationError> {
+        let code = self
+            .call("eth_getCode", serde_json::json!([address, "latest"]))
+            .await?;
+
+        decode_hex(&code)
+    }
+
+    /// Return data of calling `address` with `data`, without sending a transaction.
+    pub async fn eth_call(&self, address: &str, data: &[u8]) -> Result<Vec<u8>, VerificationError> {
+        let result = self
+            .call(
+                "eth_call",
+                serde_json::json!([
+                    { "to": address, "data": format!("0x{}", hex::encode(data)) },
+                    "latest"
+                ]),
+            )
+            .await?;
+
+        decode_hex(&result)
+    }
+
+    /// Word stored at `slot` of `address`.
+    pub async fn get_storage_at(
+        &self,
+        address: &str,
+        slot: &str,
+    ) -> Result<[u8; 32], VerificationError> {
+        let value = self
+            .call(
+                "eth_getStorageAt",
+                serde_json::json!([address, slot, "latest"]),
+            )
+            .await?;
+
+        // Nodes may strip the leading zeros, down to an odd number of digits
+        let digits = value.as_str().unwrap_or_default().trim_start_matches("0x");
+        let bytes = if digits.len() % 2 == 1 {
+            decode_hex(&Value::String(format!("0{}", digits)))?
+        } else {
+            decode_hex(&value)?
+        };
+        if bytes.len() > 32 {
+            return Err(VerificationError::Rpc(format!(
+                "storage slot {} holds {} bytes",
+                slot,
+                bytes.len()
+            )));
+        }
+        let mut word = [0u8; 32];
+        word[32 - bytes.len()..].copy_from_slice(&bytes);
+        Ok(word)
+    }
+}
+
+pub(crate) fn decode_hex(value: &Value) -> Result<Vec<u8>, VerificationError> {
+    let hex_str = value
+        .as_str()
+        .ok_or_else(|| VerificationError::Rpc(format!("expected a hex string, got {}", value)))?;
+
+    hex::decode(hex_str.trim_start_matches("0x"))
+        .map_err(|e| VerificationError::Rpc(format!("invalid hex {:?}: {}", hex_str, e)))
+}
+
+#[rocket::async_test]
+async fn test_get_code() {
+    crate::config::init_test_app_config();
+
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/")
+        .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+            "method": "eth_getCode",
+            "params": ["0x0000000000000000000000000000000000000001", "latest"],
+        })))
+        .with_body(r#"{"jsonrpc":"2.0","id":1,"result":"0x0001ff"}"#)
+        .create_async()
+        .await;
+
+    let code = RpcClient::new(&server.url())
+        .get_code("0x0000000000000000000000000000000000000001")
+        .await
+        .unwrap();
+
+    mock.assert_async().await;
+    assert_eq!(code, vec![0x00, 0x01, 0xff]);
+}
@@ -0,0 +1,272 @@
+use reqwest::Client;
+use rocket::serde::json::serde_json;
+use serde::Deserialize;
+
+use crate::errors::VerificationError;
+use crate::handlers::types::VerifyResponse;
+use crate::verification::backend::{solc_long_version, VerificationBackend, SOLC_LIST_URL};
+use crate::verification::outcome::VerificationOutcome;
+use crate::verification::{http_client, VerificationPayload};
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+struct SourcifyError {
+    custom_code: Option<String>,
+    message: String,
+}
+
+impl SourcifyError {
+    /// The message, worded so that `VerificationOutcome::from_message` recognizes it.
+    fn describe(self) -> String {
+        if self.custom_code.as_deref() == Some("already_verified") {
+            format!("Contract already verified: {}", self.message)
+        } else {
+            self.message
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+struct SubmittedJob {
+    verification_id: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct MatchedContract {
+    /// `exact_match` or `match` once verified.
+    #[serde(rename = "match")]
+    match_kind: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+struct VerificationJob {
+    is_job_completed: bool,
+    #[serde(default)]
+    contract: MatchedContract,
+    error: Option<SourcifyError>,
+}
+
+/// Client of a Sourcify-style verification server: the standard JSON input is submitted to
+/// `/v2/verify/<chain id>/<address>`, which answers with a job id whose status is polled at
+/// `/v2/verify/<job id>`.
+#[derive(Debug, Clone)]
+pub struct SourcifyClient {
+    client: Client,
+    server_url: String,
+    chain_id: u64,
+    solc_list_url: String,
+}
+
+impl SourcifyClient {
+    pub fn new(server_url: &str, chain_id: u64) -> Self {
+        Self {
+            client: http_client().clone(),
+            server_url: server_url.trim_end_matches('/').to_string(),
+            chain_id,
+            solc_list_url: SOLC_LIST_URL.to_string(),
+        }
+    }
+}
+
+/// The message of a Sourcify error body, the body itself if it isn't one.
+fn error_message(body: String) -> String {
+    match serde_json::from_str::<SourcifyError>(&body) {
+        Ok(error) => error.describe(),
+        Err(_) => body,
+    }
+}
+
+#[rocket::async_trait]
+impl VerificationBackend for SourcifyClient {
+    async fn submit_verification(
+        &self,
+        payload: &VerificationPayload,
+    ) -> Result<String, VerificationError> {
+        let solc_version = solc_long_version(
+            &self.client,
+            &self.solc_list_url,
+            &payload.compiler_solc_version,
+        )
+        .await?;
+        let response = self
+            .client
+            .post(format!(
+                "{}/v2/verify/{}/{}",
+                self.server_url, self.chain_id, payload.contract_address
+            ))
+            .json(&serde_json::json!({
+                "stdJsonInput": payload.source_code,
+                "compilerVersion": solc_version,
+                // EraVM bytecode is only reproducible with the same zksolc
+                "compilerMode": "zksync",
+                "zksolcVersion": payload.compiler_zksolc_version,
+                "contractIdentifier": payload.contract_name,
+            }))
+            .send()
+            .await?;
+
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(VerificationError::Rejected(
+                status.as_u16(),
+                error_message(body),
+            ));
+        }
+
+        serde_json::from_str::<SubmittedJob>(&body)
+            .map(|job| job.verification_id)
+            .map_err(|_| VerificationError::InvalidResponse(body))
+    }
+
+    async fn verification_status(
+        &self,
+        verification_id: &str,
+    ) -> Result<VerifyResponse, VerificationError> {
+        // job ids are UUIDs, anything else could reach another path of the server
+        if verification_id.is_empty()
+            || !verification_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-')
+        {
+            return Err(VerificationError::InvalidVerificationId(
+                verification_id.to_string(),
+            ));
+        }
+
+        let response = self
+            .client
+            .get(format!("{}/v2/verify/{}", self.server_url, verification_id))
+            .send()
+            .await?;
+
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(VerificationError::Rejected(
+                status.as_u16(),
+                error_message(body),
+            ));
+        }
+        let job: VerificationJob =
+            serde_json::from_str(&body).map_err(|_| VerificationError::InvalidResponse(body))?;
+
+        if !job.is_job_completed {
+            return Ok(VerifyResponse::new(
+                VerificationOutcome::Pending {
+                    verification_id: verification_id.to_string(),
+                },
+                format!("Verification {} is running", verification_id),
+            ));
+        }
+        if let Some(error) = job.error {
+            let message = error.describe();
+            return Ok(VerifyResponse::new(
+                VerificationOutcome::from_message(&message),
+                message,
+            ));
+        }
+
+        Ok(match job.contract.match_kind {
+            Some(match_kind) => VerifyResponse::new(
+                VerificationOutcome::Verified,
+                format!(
+                    "Verification {} succeeded ({})",
+                    verification_id, match_kind
+                ),
+            ),
+            None => VerifyResponse::new(
+                VerificationOutcome::Failed,
+                format!("Verification {} found no match", verification_id),
+            ),
+        })
+    }
+}
+
+#[rocket::async_test]
+async fn test_sourcify_verification() {
+    use crate::verification::VerificationConfig;
+
+    crate::config::init_test_app_config();
+
+    const ADDRESS: &str = "0x0000000000000000000000000000000000000001";
+    const VERIFIED_ADDRESS: &str = "0x0000000000000000000000000000000000000002";
+    let mut server = mockito::Server::new_async().await;
+    let submit = server
+        .mock("POST", format!("/v2/verify/300/{}", ADDRESS).as_str())
+        .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+            "compilerVersion": "0.8.24+commit.e11b9ed9",
+            "compilerMode": "zksync",
+            "zksolcVersion": "v1.5.6",
+            "contractIdentifier": "contracts/Greeter.sol:Greeter",
+        })))
+        .with_status(202)
+        .with_body(r#"{"verificationId":"job-1"}"#)
+        .create_async()
+        .await;
+    let status = server
+        .mock("GET", "/v2/verify/job-1")
+        .with_body(r#"{ "isJobCompleted": true, "contract": { "match": "exact_match" } }"#)
+        .create_async()
+        .await;
+    server
+        .mock(
+            "POST",
+            format!("/v2/verify/300/{}", VERIFIED_ADDRESS).as_str(),
+        )
+        .with_status(202)
+        .with_body(r#"{"verificationId":"job-2"}"#)
+        .create_async()
+        .await;
+    server
+        .mock("GET", "/v2/verify/job-2")
+        .with_body(
+            r#"{
+                "isJobCompleted": true,
+                "contract": { "match": null },
+                "error": { "customCode": "already_verified", "message": "The contract is verified" }
+            }"#,
+        )
+        .create_async()
+        .await;
+
+    let mut payload = VerificationPayload {
+        contract_address: ADDRESS.to_string(),
+        source_code: serde_json::json!({ "language": "Solidity" }),
+        code_format: "solidity-standard-json-input".to_string(),
+        contract_name: "contracts/Greeter.sol:Greeter".to_string(),
+        compiler_zksolc_version: "v1.5.6".to_string(),
+        // Already in its long form, the solc list isn't needed
+        compiler_solc_version: "0.8.24+commit.e11b9ed9".to_string(),
+        optimization_used: true,
+        constructor_arguments: "0x".to_string(),
+    };
+    let client = SourcifyClient::new(&server.url(), 300);
+
+    let response = client
+        .verify_contract(&payload, &VerificationConfig::default())
+        .await
+        .unwrap();
+    submit.assert_async().await;
+    status.assert_async().await;
+    assert_eq!(response.outcome, VerificationOutcome::Verified);
+    assert_eq!(
+        response.message,
+        "Verification job-1 succeeded (exact_match)"
+    );
+
+    payload.contract_address = VERIFIED_ADDRESS.to_string();
+    let response = client
+        .verify_contract(&payload, &VerificationConfig::default())
+        .await
+        .unwrap();
+    assert_eq!(response.outcome, VerificationOutcome::AlreadyVerified);
+
+    assert!(matches!(
+        client.verification_status("../../files/300").await,
+        Err(VerificationError::InvalidVerificationId(_))
+    ));
+}
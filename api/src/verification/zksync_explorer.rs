@@ -0,0 +1,198 @@
+use reqwest::Client;
+use serde::Deserialize;
+
+use crate::errors::VerificationError;
+use crate::handlers::types::VerifyResponse;
+use crate::verification::backend::VerificationBackend;
+use crate::verification::outcome::VerificationOutcome;
+use crate::verification::{http_client, VerificationPayload};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(crate = "rocket::serde", rename_all = "snake_case")]
+pub enum VerificationState {
+    Queued,
+    InProgress,
+    Successful,
+    Failed,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct VerificationStatus {
+    pub status: VerificationState,
+    pub error: Option<String>,
+    #[serde(default)]
+    pub compilation_errors: Option<Vec<String>>,
+}
+
+/// Client of the zkSync explorer's contract verification API: a verification is submitted
+/// to the network's `verifyURL`, which answers with an id whose status is then polled at
+/// `<verifyURL>/<id>`.
+#[derive(Debug, Clone)]
+pub struct ZksyncExplorerClient {
+    client: Client,
+    verifier_url: String,
+}
+
+impl ZksyncExplorerClient {
+    pub fn new(verifier_url: &str) -> Self {
+        Self {
+            client: http_client().clone(),
+            verifier_url: verifier_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Submits `payload`, returning the id of the verification.
+    pub async fn submit(&self, payload: &VerificationPayload) -> Result<u64, VerificationError> {
+        let response = self
+            .client
+            .post(&self.verifier_url)
+            .json(payload)
+            .send()
+            .await?;
+
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(VerificationError::Rejected(status.as_u16(), body));
+        }
+
+        body.trim()
+            .parse()
+            .map_err(|_| VerificationError::InvalidResponse(body))
+    }
+
+    pub async fn status(&self, id: u64) -> Result<VerificationStatus, VerificationError> {
+        let response = self
+            .client
+            .get(format!("{}/{}", self.verifier_url, id))
+            .send()
+            .await?;
+
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(VerificationError::Rejected(status.as_u16(), body));
+        }
+
+        rocket::serde::json::from_str(&body).map_err(|_| VerificationError::InvalidResponse(body))
+    }
+}
+
+#[rocket::async_trait]
+impl VerificationBackend for ZksyncExplorerClient {
+    async fn submit_verification(
+        &self,
+        payload: &VerificationPayload,
+    ) -> Result<String, VerificationError> {
+        self.submit(payload).await.map(|id| id.to_string())
+    }
+
+    async fn verification_status(
+        &self,
+        verification_id: &str,
+    ) -> Result<VerifyResponse, VerificationError> {
+        let id = verification_id
+            .parse()
+            .map_err(|_| VerificationError::InvalidVerificationId(verification_id.to_string()))?;
+        let status = self.status(id).await?;
+
+        let outcome = VerificationOutcome::from_status(id, &status);
+        let message = match outcome {
+            VerificationOutcome::Verified => format!("Verification {} succeeded", id),
+            VerificationOutcome::Pending { .. } => {
+                format!("Verification {} is {:?}", id, status.status)
+            }
+            _ => status_message(&status),
+        };
+
+        Ok(VerifyResponse::new(outcome, message))
+    }
+}
+
+/// The error of a verification along with its compilation errors.
+fn status_message(status: &VerificationStatus) -> String {
+    let mut message = status.error.clone().unwrap_or_default();
+    for error in status.compilation_errors.iter().flatten() {
+        message.push('\n');
+        message.push_str(error);
+    }
+
+    message
+}
+
+#[rocket::async_test]
+async fn test_verify_polls_until_final_status() {
+    use crate::handlers::types::{CompiledFile, Libraries, VerificationRequest, VerifyConfig};
+    use crate::verification::constructor_args::ConstructorArgs;
+    use crate::verification::{OptimizerSettings, VerificationConfig};
+
+    crate::config::init_test_app_config();
+
+    let mut server = mockito::Server::new_async().await;
+    let submit = server
+        .mock("POST", "/contract_verification")
+        .match_body(mockito::Matcher::PartialJson(rocket::serde::json::json!({
+            "contractName": "contracts/Greeter.sol:Greeter",
+            "compilerZksolcVersion": "v1.5.6",
+            "constructorArguments": "0x",
+        })))
+        .with_body("42")
+        .create_async()
+        .await;
+    // In progress on the first poll, failed from then on
+    let polls = std::sync::atomic::AtomicUsize::new(0);
+    let status = server
+        .mock("GET", "/contract_verification/42")
+        .with_body_from_request(move |_| {
+            match polls.fetch_add(1, std::sync::atomic::Ordering::SeqCst) {
+                0 => br#"{"status":"in_progress"}"#.to_vec(),
+                _ => br#"{"status":"failed","error":"Bytecode mismatch"}"#.to_vec(),
+            }
+        })
+        .expect(2)
+        .create_async()
+        .await;
+
+    let request = VerificationRequest {
+        config: VerifyConfig {
+            zksolc_version: "1.5.6".to_string(),
+            solc_version: None,
+            network: "sepolia".to_string(),
+            contract_address: "0x0000000000000000000000000000000000000001".to_string(),
+            inputs: vec![],
+            constructor_args: None,
+            check_bytecode: None,
+            deployed_bytecode: None,
+            optimizer: OptimizerSettings::default(),
+            user_libraries: vec![],
+            verifier: None,
+        },
+        contracts: vec![CompiledFile {
+            file_name: "contracts/Greeter.sol".to_string(),
+            file_content: "contract Greeter {}".to_string(),
+            is_contract: true,
+        }],
+        target_contract: Some("contracts/Greeter.sol:Greeter".to_string()),
+        abi: None,
+        compilation_id: None,
+        target_path: None,
+        proxy: None,
+    };
+    let constructor_args = ConstructorArgs::from_request(&request).unwrap();
+    let payload =
+        VerificationPayload::new(&request, "0.8.24", &Libraries::new(), &constructor_args).unwrap();
+
+    let config = VerificationConfig {
+        poll_interval: 10,
+        ..VerificationConfig::default()
+    };
+    let client = ZksyncExplorerClient::new(&format!("{}/contract_verification", server.url()));
+
+    let response = client.verify_contract(&payload, &config).await.unwrap();
+
+    submit.assert_async().await;
+    status.assert_async().await;
+    assert_eq!(response.outcome, VerificationOutcome::BytecodeMismatch);
+    assert_eq!(response.message, "Bytecode mismatch");
+}
@@ -6,15 +6,26 @@ use rocket::tokio::task::JoinHandle;
 use rocket::tokio::time;
 use rocket::tokio::time::sleep;
 use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use tracing::info;
+use std::time::Instant;
+use tracing::{info, info_span, Instrument, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
 use uuid::Uuid;
 
+use crate::config::app_config;
 use crate::errors::ApiError;
 use crate::handlers;
 use crate::handlers::types::{ApiCommand, ApiCommandResult};
+use crate::handlers::SPAWN_SEMAPHORE;
 use crate::metrics::Metrics;
-use crate::utils::lib::DURATION_TO_PURGE;
+use crate::request_id::RequestId;
+use crate::utils::job_log::JobLog;
+use crate::utils::lib::{directory_size, SOL_ROOT};
+
+const SUPERVISOR_INTERVAL_MILLIS: u64 = 2000;
+// Walking the workspaces is comparatively expensive, only do it every 15 supervisor ticks
+const DISK_USAGE_INTERVAL_TICKS: u64 = 15;
 
 #[derive(Debug)]
 pub enum ProcessState {
@@ -24,6 +35,17 @@ pub enum ProcessState {
     Error(ApiError),
 }
 
+impl ProcessState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ProcessState::New => "new",
+            ProcessState::Running => "running",
+            ProcessState::Completed(_) => "completed",
+            ProcessState::Error(_) => "error",
+        }
+    }
+}
+
 impl Display for ProcessState {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -35,16 +57,45 @@ impl Display for ProcessState {
     }
 }
 
-pub type ProcessStateMap = SkipMap<Uuid, ProcessState>;
+/// State of a job along with the id of the request that created it and its captured output.
+#[derive(Debug)]
+pub struct ProcessEntry {
+    pub request_id: String,
+    pub state: ProcessState,
+    pub job_log: Arc<JobLog>,
+}
+
+impl Display for ProcessEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.state {
+            ProcessState::Error(_) => write!(f, "{} (request id: {})", self.state, self.request_id),
+            _ => write!(f, "{}", self.state),
+        }
+    }
+}
+
+pub type ProcessStateMap = SkipMap<Uuid, ProcessEntry>;
 pub type Timestamp = u64;
 
+#[derive(Debug)]
+pub struct QueuedCommand {
+    pub process_id: Uuid,
+    pub command: ApiCommand,
+    pub enqueued_at: Instant,
+    pub request_id: String,
+    pub job_log: Arc<JobLog>,
+    /// Trace context of the request that enqueued the command, the job span is its child.
+    pub trace_context: opentelemetry::Context,
+}
+
 #[derive(Debug)]
 pub struct WorkerEngine {
     pub num_workers: u32,
     pub worker_threads: Vec<JoinHandle<()>>,
-    pub arc_command_queue: Arc<ArrayQueue<(Uuid, ApiCommand)>>,
+    pub arc_command_queue: Arc<ArrayQueue<QueuedCommand>>,
     pub arc_process_states: Arc<ProcessStateMap>,
     pub arc_timestamps_to_purge: Arc<ArrayQueue<(Uuid, Timestamp)>>,
+    pub arc_busy_workers: Arc<AtomicUsize>,
     pub is_supervisor_enabled: Arc<Mutex<bool>>,
     pub supervisor_thread: Arc<Option<JoinHandle<()>>>,
     pub metrics: Metrics,
@@ -54,7 +105,7 @@ impl WorkerEngine {
     pub fn new(num_workers: u32, queue_capacity: usize, metrics: Metrics) -> Self {
         // Create a queue instance
 
-        let queue: ArrayQueue<(Uuid, ApiCommand)> = ArrayQueue::new(queue_capacity);
+        let queue: ArrayQueue<QueuedCommand> = ArrayQueue::new(queue_capacity);
         let arc_command_queue = Arc::new(queue);
 
         // Create a process state map instance (NOTE: how to implement purging from this map???)
@@ -71,6 +122,9 @@ impl WorkerEngine {
         let timestamps_to_purge: ArrayQueue<(Uuid, Timestamp)> = ArrayQueue::new(queue_capacity);
         let arc_timestamps_to_purge = Arc::new(timestamps_to_purge);
 
+        // Number of workers currently executing a command
+        let arc_busy_workers = Arc::new(AtomicUsize::new(0));
+
         WorkerEngine {
             num_workers,
             arc_command_queue,
@@ -78,6 +132,7 @@ impl WorkerEngine {
             worker_threads,
             supervisor_thread: Arc::new(None),
             arc_timestamps_to_purge,
+            arc_busy_workers,
             is_supervisor_enabled,
             metrics,
         }
@@ -89,12 +144,14 @@ impl WorkerEngine {
             let arc_clone = self.arc_command_queue.clone();
             let arc_states = self.arc_process_states.clone();
             let arc_timestamps_to_purge = self.arc_timestamps_to_purge.clone();
+            let arc_busy_workers = self.arc_busy_workers.clone();
             let metrics_clone = self.metrics.clone();
             self.worker_threads.push(tokio::spawn(async move {
                 WorkerEngine::worker(
                     arc_clone,
                     arc_states,
                     arc_timestamps_to_purge,
+                    arc_busy_workers,
                     metrics_clone,
                 )
                 .await;
@@ -102,43 +159,50 @@ impl WorkerEngine {
         }
 
         // start supervisor thread
-        {
-            let is_supervisor_enabled = self.is_supervisor_enabled.clone();
-            let arc_process_states = self.arc_process_states.clone();
-            let process_timestamps_to_purge = self.arc_timestamps_to_purge.clone();
-
-            self.supervisor_thread = Arc::new(Some(tokio::spawn(async move {
-                WorkerEngine::supervisor(
-                    is_supervisor_enabled,
-                    arc_process_states,
-                    process_timestamps_to_purge,
-                )
-                .await;
-            })));
-        }
+        self.supervisor_thread = Arc::new(Some(self.spawn_supervisor()));
     }
 
-    pub async fn enable_supervisor_thread(&mut self) {
+    fn spawn_supervisor(&self) -> JoinHandle<()> {
         let is_supervisor_enabled = self.is_supervisor_enabled.clone();
+        let arc_command_queue = self.arc_command_queue.clone();
         let arc_process_states = self.arc_process_states.clone();
         let process_timestamps_to_purge = self.arc_timestamps_to_purge.clone();
-        let mut is_enabled = self.is_supervisor_enabled.lock().await;
-        *is_enabled = true;
-        self.supervisor_thread = Arc::new(Some(tokio::spawn(async move {
+        let arc_busy_workers = self.arc_busy_workers.clone();
+        let num_workers = self.num_workers;
+        let metrics = self.metrics.clone();
+
+        tokio::spawn(async move {
             WorkerEngine::supervisor(
                 is_supervisor_enabled,
+                arc_command_queue,
                 arc_process_states,
                 process_timestamps_to_purge,
+                arc_busy_workers,
+                num_workers,
+                metrics,
             )
             .await;
-        })));
+        })
+    }
+
+    pub async fn enable_supervisor_thread(&mut self) {
+        {
+            let mut is_enabled = self.is_supervisor_enabled.lock().await;
+            *is_enabled = true;
+        }
+        self.supervisor_thread = Arc::new(Some(self.spawn_supervisor()));
     }
 
     pub async fn supervisor(
         is_supervisor_enabled: Arc<Mutex<bool>>,
+        arc_command_queue: Arc<ArrayQueue<QueuedCommand>>,
         arc_process_states: Arc<ProcessStateMap>,
         process_timestamps_to_purge: Arc<ArrayQueue<(Uuid, Timestamp)>>,
+        arc_busy_workers: Arc<AtomicUsize>,
+        num_workers: u32,
+        metrics: Metrics,
     ) {
+        let mut tick: u64 = 0;
         loop {
             let is_supervisor_enabled = is_supervisor_enabled.lock().await;
             if !*is_supervisor_enabled {
@@ -160,7 +224,66 @@ impl WorkerEngine {
                 }
             }
 
-            sleep(time::Duration::from_millis(2000)).await;
+            WorkerEngine::update_metrics(
+                &metrics,
+                &arc_command_queue,
+                &arc_process_states,
+                &process_timestamps_to_purge,
+                arc_busy_workers.load(Ordering::Relaxed),
+                num_workers,
+            );
+
+            if tick.is_multiple_of(DISK_USAGE_INTERVAL_TICKS) {
+                if let Ok(disk_usage) =
+                    tokio::task::spawn_blocking(|| directory_size(SOL_ROOT)).await
+                {
+                    metrics.workspace_disk_usage_bytes.set(disk_usage as i64);
+                }
+            }
+            tick += 1;
+
+            sleep(time::Duration::from_millis(SUPERVISOR_INTERVAL_MILLIS)).await;
+        }
+    }
+
+    fn update_metrics(
+        metrics: &Metrics,
+        arc_command_queue: &ArrayQueue<QueuedCommand>,
+        arc_process_states: &ProcessStateMap,
+        process_timestamps_to_purge: &ArrayQueue<(Uuid, Timestamp)>,
+        busy_workers: usize,
+        num_workers: u32,
+    ) {
+        metrics.queue_depth.set(arc_command_queue.len() as i64);
+        metrics
+            .pending_purge
+            .set(process_timestamps_to_purge.len() as i64);
+        metrics
+            .spawn_permits_available
+            .set(SPAWN_SEMAPHORE.available_permits() as i64);
+
+        let busy_workers = busy_workers as i64;
+        metrics
+            .workers
+            .with_label_values(&["busy"])
+            .set(busy_workers);
+        metrics
+            .workers
+            .with_label_values(&["idle"])
+            .set(i64::from(num_workers) - busy_workers);
+
+        let mut counts = [("new", 0), ("running", 0), ("completed", 0), ("error", 0)];
+        for entry in arc_process_states.iter() {
+            let label = entry.value().state.label();
+            if let Some((_, count)) = counts.iter_mut().find(|(state, _)| *state == label) {
+                *count += 1;
+            }
+        }
+        for (state, count) in counts {
+            metrics
+                .process_states
+                .with_label_values(&[state])
+                .set(count);
         }
     }
 
@@ -175,32 +298,79 @@ impl WorkerEngine {
         self.supervisor_thread = Arc::new(None);
     }
 
-    pub fn enqueue_command(&self, command: ApiCommand) -> Result<Uuid, String> {
+    pub fn enqueue_command(
+        &self,
+        command: ApiCommand,
+        request_id: &RequestId,
+    ) -> Result<Uuid, String> {
         let uuid = Uuid::new_v4();
-
-        self.arc_process_states.insert(uuid, ProcessState::New);
-
-        match self.arc_command_queue.push((uuid, command)) {
+        let job_log = Arc::new(JobLog::default());
+
+        self.arc_process_states.insert(
+            uuid,
+            ProcessEntry {
+                request_id: request_id.0.clone(),
+                state: ProcessState::New,
+                job_log: job_log.clone(),
+            },
+        );
+
+        let queued_command = QueuedCommand {
+            process_id: uuid,
+            command,
+            enqueued_at: Instant::now(),
+            request_id: request_id.0.clone(),
+            job_log,
+            trace_context: Span::current().context(),
+        };
+
+        match self.arc_command_queue.push(queued_command) {
             Ok(()) => Ok(uuid),
-            Err((uuid, command)) => Err(format!(
+            Err(QueuedCommand {
+                process_id,
+                command,
+                ..
+            }) => Err(format!(
                 "Error enqueueing command {:?} in process {:?}",
-                command, uuid
+                command, process_id
             )),
         }
     }
 
+    /// Span covering the execution of a queued command, continuing the trace of the
+    /// request that enqueued it.
+    fn job_span(process_id: Uuid, request_id: &str, trace_context: opentelemetry::Context) -> Span {
+        let job_span = info_span!("job", %process_id, request_id);
+        if let Err(e) = job_span.set_parent(trace_context) {
+            debug!(
+                "Failed to link job {} to its request trace: {}",
+                process_id, e
+            );
+        }
+
+        job_span
+    }
+
     // worker function
     pub async fn worker(
-        arc_command_queue: Arc<ArrayQueue<(Uuid, ApiCommand)>>,
+        arc_command_queue: Arc<ArrayQueue<QueuedCommand>>,
         arc_process_states: Arc<ProcessStateMap>,
         arc_timestamps_to_purge: Arc<ArrayQueue<(Uuid, Timestamp)>>,
+        arc_busy_workers: Arc<AtomicUsize>,
         metrics: Metrics,
     ) {
         info!("Starting worker thread...");
         'worker_loop: loop {
             // read process ID and command from queue
             match arc_command_queue.pop() {
-                Some((process_id, command)) => {
+                Some(QueuedCommand {
+                    process_id,
+                    command,
+                    enqueued_at,
+                    request_id,
+                    job_log,
+                    trace_context,
+                }) => {
                     debug!("Command received: {:?}", command);
 
                     match command {
@@ -209,27 +379,64 @@ impl WorkerEngine {
                         }
                         _ => {
                             // update process state
-                            arc_process_states.insert(process_id, ProcessState::Running);
-
-                            match handlers::dispatch_command(command, &metrics).await {
+                            arc_process_states.insert(
+                                process_id,
+                                ProcessEntry {
+                                    request_id: request_id.clone(),
+                                    state: ProcessState::Running,
+                                    job_log: job_log.clone(),
+                                },
+                            );
+
+                            let queue_wait = enqueued_at.elapsed();
+                            arc_busy_workers.fetch_add(1, Ordering::Relaxed);
+                            let job_span =
+                                WorkerEngine::job_span(process_id, &request_id, trace_context);
+                            let result = handlers::dispatch_command(
+                                command,
+                                &metrics,
+                                queue_wait,
+                                &request_id,
+                                &job_log,
+                            )
+                            .instrument(job_span)
+                            .await;
+                            arc_busy_workers.fetch_sub(1, Ordering::Relaxed);
+
+                            match result {
                                 Ok(result) => {
-                                    arc_process_states
-                                        .insert(process_id, ProcessState::Completed(result));
+                                    arc_process_states.insert(
+                                        process_id,
+                                        ProcessEntry {
+                                            request_id,
+                                            state: ProcessState::Completed(result),
+                                            job_log,
+                                        },
+                                    );
 
                                     arc_timestamps_to_purge
                                         .push((
                                             process_id,
-                                            crate::utils::lib::timestamp() + DURATION_TO_PURGE,
+                                            crate::utils::lib::timestamp()
+                                                + app_config().worker.result_ttl,
                                         ))
                                         .unwrap();
                                 }
                                 Err(e) => {
-                                    arc_process_states.insert(process_id, ProcessState::Error(e));
+                                    arc_process_states.insert(
+                                        process_id,
+                                        ProcessEntry {
+                                            request_id,
+                                            state: ProcessState::Error(e),
+                                            job_log,
+                                        },
+                                    );
 
                                     arc_timestamps_to_purge
                                         .push((
                                             process_id,
-                                            crate::utils::lib::timestamp() + DURATION_TO_PURGE,
+                                            crate::utils::lib::timestamp()
+                                                + app_config().worker.result_ttl,
                                         ))
                                         .unwrap();
                                 }
@@ -246,3 +453,45 @@ impl WorkerEngine {
         info!("Worker thread finished...");
     }
 }
+
+#[test]
+fn test_job_span_continues_request_trace() {
+    use crate::metrics::create_metrics;
+    use crate::tracing_log::otel_layer;
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider};
+    use tracing_subscriber::prelude::*;
+
+    let exporter = InMemorySpanExporter::default();
+    let provider = SdkTracerProvider::builder()
+        .with_simple_exporter(exporter.clone())
+        .build();
+    let subscriber = tracing_subscriber::registry().with(otel_layer(&provider));
+    let engine = WorkerEngine::new(1, 1, create_metrics(prometheus::Registry::new()).unwrap());
+
+    tracing::subscriber::with_default(subscriber, || {
+        info_span!("compile_async").in_scope(|| {
+            engine
+                .enqueue_command(
+                    ApiCommand::CompilerVersion,
+                    &RequestId("request-id".to_string()),
+                )
+                .unwrap();
+        });
+
+        let queued_command = engine.arc_command_queue.pop().unwrap();
+        WorkerEngine::job_span(
+            queued_command.process_id,
+            &queued_command.request_id,
+            queued_command.trace_context,
+        )
+        .in_scope(|| info_span!("workspace_setup").in_scope(|| {}));
+    });
+
+    let spans = exporter.get_finished_spans().unwrap();
+    let span = |name: &str| spans.iter().find(|span| span.name == name).unwrap();
+    let (request, job, setup) = (span("compile_async"), span("job"), span("workspace_setup"));
+
+    assert_eq!(job.span_context.trace_id(), request.span_context.trace_id());
+    assert_eq!(job.parent_span_id, request.span_context.span_id());
+    assert_eq!(setup.parent_span_id, job.span_context.span_id());
+}
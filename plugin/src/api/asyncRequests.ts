@@ -1,6 +1,11 @@
 import { apiUrl } from '../utils/network'
 
 async function handleAsyncApiResponse(response: Response, getterMethod: string): Promise<Response> {
+  if (response.status === 429) {
+    const retryAfter = response.headers.get('Retry-After') ?? response.headers.get('RateLimit-Reset')
+    throw new Error(`Too many requests, please retry in ${retryAfter ?? 'a few'} seconds`)
+  }
+
   const pid = await response.text()
 
   try {
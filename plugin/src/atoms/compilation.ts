@@ -7,6 +7,10 @@ const isCompilingAtom = atom<boolean>(false)
 const compilationTypeAtom = atom<CompilationType>('NONE')
 type CompilationKeys = 'status' | 'isCompiling' | 'hashDir' | 'errorMessages' | 'compilationType'
 const compileErrorMessagesAtom = atom<string[]>([])
+// Set by the backend on successful compilations, lets verification skip re-uploading sources
+const compilationIdAtom = atom<string | null>(null)
+// Sources folder of the last compilation, verification has to compile from the same one
+const compilationTargetPathAtom = atom<string | null>(null)
 
 interface SetCompilationValue {
   key: CompilationKeys
@@ -51,6 +55,8 @@ export {
   compilationAtom,
   compileErrorMessagesAtom,
   compilationTypeAtom,
+  compilationIdAtom,
+  compilationTargetPathAtom,
   type SetCompilationValue,
   type CompilationKeys
 }
@@ -7,6 +7,8 @@ export {
   type CompilationKeys,
   type SetCompilationValue,
   compilationTypeAtom,
+  compilationIdAtom,
+  compilationTargetPathAtom,
   type CompilationType
 } from './compilation'
 export { contractsAtom, selectedContractAtom } from './compiledContracts'
@@ -29,6 +31,7 @@ export {
   type VerificationKeys,
   isVerifyingAtom,
   verificatationStatusAtom,
-  verificationAtom
+  verificationAtom,
+  verificationNetworksAtom
 } from './verification'
-export { solidityVersionAtom, versionsAtom } from './version'
+export { solidityVersionAtom, versionsAtom, versionsInfoAtom } from './version'
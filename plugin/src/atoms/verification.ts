@@ -1,9 +1,13 @@
 import { atom } from 'jotai'
+import { type VerificationNetwork } from '../types/contracts'
 
 const verificatationStatusAtom = atom<string>('Verifying...')
 
 const isVerifyingAtom = atom<boolean>(false)
 
+// Networks the compilation server can verify contracts on
+const verificationNetworksAtom = atom<VerificationNetwork[]>([])
+
 type VerificationKeys = 'status' | 'isVerifying'
 
 interface SetVerificationValue {
@@ -30,4 +34,11 @@ const verificationAtom = atom(
   }
 )
 
-export { verificatationStatusAtom, isVerifyingAtom, verificationAtom, type SetVerificationValue, type VerificationKeys }
+export {
+  verificatationStatusAtom,
+  isVerifyingAtom,
+  verificationAtom,
+  verificationNetworksAtom,
+  type SetVerificationValue,
+  type VerificationKeys
+}
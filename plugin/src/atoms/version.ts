@@ -1,7 +1,10 @@
 import { atom } from 'jotai'
+import { type ZksolcVersionInfo } from '../types/contracts'
 
 const solidityVersionAtom = atom<string>('latest')
 
 const versionsAtom = atom<string[]>([])
 
-export { solidityVersionAtom, versionsAtom }
+const versionsInfoAtom = atom<ZksolcVersionInfo[]>([])
+
+export { solidityVersionAtom, versionsAtom, versionsInfoAtom }
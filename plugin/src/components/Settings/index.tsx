@@ -1,13 +1,21 @@
 import React from 'react'
 import { BsChevronDown } from 'react-icons/bs'
 import { useAtom, useAtomValue } from 'jotai'
-import { solidityVersionAtom, versionsAtom } from '@/atoms'
+import { solidityVersionAtom, versionsAtom, versionsInfoAtom } from '@/atoms'
 import * as Dropdown from '@/ui_components/Dropdown'
 import './settings.css'
 
 export const Settings = () => {
   const [solidityVersion, setSolidityVersion] = useAtom(solidityVersionAtom)
   const versions = useAtomValue(versionsAtom)
+  const versionsInfo = useAtomValue(versionsInfoAtom)
+
+  const versionLabel = (version: string): string => {
+    const info = versionsInfo.find((info) => info.version === version)
+    if (info?.deprecated === true) return `${version} (deprecated)`
+    if (info?.default === true) return `${version} (default)`
+    return version
+  }
 
   return (
     <div className="settings-wrapper">
@@ -34,7 +42,7 @@ export const Settings = () => {
                           setSolidityVersion(v)
                         }}
                       >
-                        {v}
+                        {versionLabel(v)}
                       </Dropdown.Item>
                     )
                   })}
@@ -11,6 +11,8 @@ import {
   compileErrorMessagesAtom,
   deployStatusAtom,
   compilationTypeAtom,
+  compilationIdAtom,
+  compilationTargetPathAtom,
   CompilationType
 } from '@/atoms'
 import {
@@ -43,6 +45,8 @@ export const Compilation = ({ setAccordian }: CompilationProps) => {
   const setCompileStatus = useSetAtom(compileStatusAtom)
   const setIsCompiling = useSetAtom(isCompilingAtom)
   const setCompileErrorMessages = useSetAtom(compileErrorMessagesAtom)
+  const setCompilationId = useSetAtom(compilationIdAtom)
+  const setCompilationTargetPath = useSetAtom(compilationTargetPathAtom)
 
   const [compilationType, setCompilationType] = useAtom(compilationTypeAtom)
 
@@ -95,6 +99,15 @@ export const Compilation = ({ setAccordian }: CompilationProps) => {
       }
 
       const compileResult = JSON.parse(await response.text()) as CompilationResult
+      setCompilationId(compileResult.compilation_id ?? null)
+      setCompilationTargetPath(workspaceContents.target_path ?? null)
+
+      for (const warning of compileResult.warnings ?? []) {
+        await remixClient.terminal.log({
+          value: warning,
+          type: 'warn'
+        })
+      }
 
       if (compileResult.status !== 'Success') {
         await emitErrorToRemix(compileResult)
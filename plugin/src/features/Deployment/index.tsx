@@ -11,6 +11,7 @@ import ConstructorInput, { ContractInputType } from '@/components/ConstructorInp
 import { type VerificationResult, type DeployedContract } from '@/types/contracts'
 import { mockManualChain, type Transaction } from '@/types/transaction'
 import { asyncPost } from '@/api/asyncRequests'
+import { apiUrl } from '@/utils/network'
 import {
   transactionsAtom,
   accountAtom,
@@ -23,7 +24,10 @@ import {
   solidityVersionAtom,
   deployStatusAtom,
   contractsAtom,
-  selectedContractAtom
+  selectedContractAtom,
+  verificationNetworksAtom,
+  compilationIdAtom,
+  compilationTargetPathAtom
 } from '@/atoms'
 import {
   currentFilenameAtom,
@@ -65,6 +69,9 @@ export const Deployment: React.FC<DeploymentProps> = ({ setActiveTab }) => {
   const solidityVersion = useAtomValue(solidityVersionAtom)
   const env = useAtomValue(envAtom)
   const provider = useAtomValue(providerAtom)
+  const verificationNetworks = useAtomValue(verificationNetworksAtom)
+  const compilationId = useAtomValue(compilationIdAtom)
+  const compilationTargetPath = useAtomValue(compilationTargetPathAtom)
 
   const remixClient = useAtomValue(remixClientAtom)
 
@@ -82,11 +89,9 @@ export const Deployment: React.FC<DeploymentProps> = ({ setActiveTab }) => {
   }, [selectedContract])
 
   useEffect(() => {
-    let name: string | undefined
-    if (provider?.network?.chainId === 300) name = 'sepolia'
-    if (provider?.network?.chainId === 324) name = 'mainnet'
-    setSelectedChainName(name)
-  }, [provider, env])
+    const network = verificationNetworks.find((network) => network.chain_id === provider?.network?.chainId)
+    setSelectedChainName(network?.name)
+  }, [provider, env, verificationNetworks])
 
   async function verify(contract: DeployedContract | null): Promise<void> {
     if (!contract) {
@@ -106,26 +111,60 @@ export const Deployment: React.FC<DeploymentProps> = ({ setActiveTab }) => {
           contract_address: contract.address,
           inputs: parseContractInputs(inputs)
         },
-        contracts: [] as Array<{ file_name: string; file_content: string; is_contract: boolean }>
+        contracts: [] as Array<{ file_name: string; file_content: string; is_contract: boolean }>,
+        compilation_id: compilationId ?? undefined,
+        target_path: compilationTargetPath ?? undefined,
+        target_contract: `${contract.sourceName}:${contract.contractName}`,
+        // lets the server check and encode the constructor arguments
+        abi: contract.abi
       }
 
-      console.log(`currentWorkspacePath: ${currentWorkspacePath}`)
-      const workspaceFiles = await remixClient.fileManager.readdir(`${currentWorkspacePath}/`)
-      console.log(`workspaceFiles: ${JSON.stringify(workspaceFiles)}`)
+      const uploadSources = async (): Promise<void> => {
+        console.log(`currentWorkspacePath: ${currentWorkspacePath}`)
+        const workspaceFiles = await remixClient.fileManager.readdir(`${currentWorkspacePath}/`)
+        console.log(`workspaceFiles: ${JSON.stringify(workspaceFiles)}`)
 
-      workspaceContents.contracts = await getAllContractFiles(remixClient, currentWorkspacePath)
+        workspaceContents.compilation_id = undefined
+        workspaceContents.contracts = await getAllContractFiles(remixClient, currentWorkspacePath)
+      }
 
-      const response = await asyncPost('verify-async', 'verify-result', workspaceContents)
+      const requestVerification = async (): Promise<VerificationResult> => {
+        const response = await asyncPost('verify-async', 'verify-result', workspaceContents)
 
-      if (!response.ok) {
-        setDeployStatus('ERROR')
-        throw new Error('Could not reach solidity verification server')
+        if (!response.ok) {
+          setDeployStatus('ERROR')
+          throw new Error('Could not reach solidity verification server')
+        }
+
+        // get Json body from response
+        return JSON.parse(await response.text()) as VerificationResult
       }
 
-      // get Json body from response
-      const verificationResult = JSON.parse(await response.text()) as VerificationResult
+      // the server keeps successful compilations for a while, otherwise send the sources along
+      if (workspaceContents.compilation_id === undefined) {
+        await uploadSources()
+      }
+      let verificationResult = await requestVerification()
+      if (
+        workspaceContents.compilation_id !== undefined &&
+        verificationResult.status !== 'Success' &&
+        verificationResult.message.includes('not found, it may have expired')
+      ) {
+        await uploadSources()
+        verificationResult = await requestVerification()
+      }
 
-      if (verificationResult.status !== 'Success') {
+      if (verificationResult.status === 'Pending') {
+        const statusUrl = `${apiUrl}/verify-status/${verificationResult.verification_id ?? ''}?network=${
+          selectedChainName ?? ''
+        }`
+        await remixClient.terminal.log({
+          value: `Verification is still pending at the explorer, its status is available at ${statusUrl}`,
+          type: 'warn'
+        })
+        await remixClient.call('notification' as any, 'toast', 'Verification pending.')
+        setDeployStatus('DONE')
+      } else if (verificationResult.status !== 'Success') {
         setDeployStatus('ERROR')
         await remixClient.terminal.log({
           value: verificationResult.message,
@@ -189,7 +228,10 @@ export const Deployment: React.FC<DeploymentProps> = ({ setActiveTab }) => {
         })
 
         await remixClient.terminal.log({
-          value: 'Verification successful.',
+          value:
+            verificationResult.outcome === 'already_verified'
+              ? 'Contract was already verified.'
+              : 'Verification successful.',
           type: 'info'
         })
 
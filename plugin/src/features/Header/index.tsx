@@ -6,8 +6,10 @@ import { remixClientAtom } from '../../stores/remixClient'
 import './style.css'
 import useAsync from '@/hooks/useAsync'
 import useAsyncFn from '@/hooks/useAsyncFn'
-import { solidityVersionAtom, versionsAtom } from '@/atoms'
+import { solidityVersionAtom, verificationNetworksAtom, versionsAtom, versionsInfoAtom } from '@/atoms'
+import { type ZksolcVersionInfo } from '@/types/contracts'
 import useTimeoutFn from '@/hooks/useTimeoutFn'
+import { fetchVerificationNetworks } from '@/utils/custom_chains'
 
 const envViteVersion: string | undefined = import.meta.env.VITE_VERSION
 const pluginVersion = envViteVersion !== undefined ? `v${envViteVersion}` : 'v0.2.5'
@@ -18,6 +20,8 @@ export const Header = () => {
   const remixClient = useAtomValue(remixClientAtom)
   const setSolidityVersion = useSetAtom(solidityVersionAtom)
   const [versions, setVersions] = useAtom(versionsAtom)
+  const setVersionsInfo = useSetAtom(versionsInfoAtom)
+  const setVerificationNetworks = useSetAtom(verificationNetworksAtom)
 
   useAsync(async () => {
     try {
@@ -61,6 +65,14 @@ export const Header = () => {
     }
   }, [remixClient])
 
+  useAsync(async () => {
+    try {
+      setVerificationNetworks(await fetchVerificationNetworks())
+    } catch (error) {
+      console.error(error)
+    }
+  }, [])
+
   const [, refetchVersions] = useAsyncFn(async () => {
     try {
       await remixClient.call(
@@ -68,17 +80,20 @@ export const Header = () => {
         'toast',
         `🟢 Fetching solidity versions from the compilation server at ${apiUrl}`
       )
-      const response = await fetch(`${apiUrl}/allowed_versions`, {
+      const response = await fetch(`${apiUrl}/versions`, {
         method: 'GET',
         redirect: 'follow',
         headers: {
           'Content-Type': 'application/octet-stream'
         }
       })
-      const allowedVersions = await response.json()
+      const versionsInfo = (await response.json()) as ZksolcVersionInfo[]
+      const allowedVersions = versionsInfo.map((info) => info.version)
+      setVersionsInfo(versionsInfo)
       setVersions(allowedVersions)
-      if (allowedVersions.length > 0) {
-        setSolidityVersion(allowedVersions[0])
+      const defaultVersion = versionsInfo.find((info) => info.default) ?? versionsInfo[0]
+      if (defaultVersion !== undefined) {
+        setSolidityVersion(defaultVersion.version)
       }
 
       return allowedVersions
@@ -27,11 +27,45 @@ interface CompilationResult {
   status: string
   message: string
   file_content: CompiledArtifact[]
+  warnings?: string[]
+  compilation_id?: string
 }
 
+interface ZksolcVersionInfo {
+  version: string
+  default: boolean
+  deprecated: boolean
+  deprecated_since: string | null
+  removal_date: string | null
+  solc_range: string | null
+  features: string[]
+}
+
+type VerificationOutcome =
+  | 'verified'
+  | 'already_verified'
+  | 'pending'
+  | 'bytecode_mismatch'
+  | 'contract_not_found'
+  | 'verifier_unavailable'
+  | 'failed'
+
 interface VerificationResult {
   status: string
   message: string
+  outcome?: VerificationOutcome
+  // Set on pending verifications, poll `/verify-status/<verification_id>` for the result
+  verification_id?: string
+}
+
+interface VerificationNetwork {
+  name: string
+  chain_id: number
+  rpc_url: string
+  l1_network: string
+  verifier_url: string | null
+  etherscan_url: string | null
+  sourcify_url: string | null
 }
 
 interface CompiledArtifact {
@@ -69,5 +103,8 @@ export type {
   DeployedContract,
   CompilationResult,
   VerificationResult,
-  CompiledArtifact
+  CompiledArtifact,
+  VerificationNetwork,
+  VerificationOutcome,
+  ZksolcVersionInfo
 }
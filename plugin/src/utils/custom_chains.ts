@@ -1,4 +1,18 @@
 import {defineChain} from 'viem'
+import {type VerificationNetwork} from '@/types/contracts'
+import {apiUrl} from './network'
+
+// Networks registered on the compilation server, the ones contracts can be verified on
+export async function fetchVerificationNetworks(): Promise<VerificationNetwork[]> {
+    const response = await fetch(`${apiUrl}/networks`, {
+        method: 'GET',
+        redirect: 'follow',
+        headers: {
+            'Content-Type': 'application/octet-stream'
+        }
+    })
+    return (await response.json()) as VerificationNetwork[]
+}
 
 export const zkCandySepoliaTestnet = defineChain({
     id: 302,